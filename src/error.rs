@@ -1,6 +1,9 @@
-use std::fmt::Display;
+use std::{
+    fmt::Display,
+    path::{Path, PathBuf},
+};
 
-use crate::parse_file::Version;
+use crate::{parse_file::Version, LabelName};
 use thiserror::Error;
 
 #[derive(Error, Debug)]
@@ -11,7 +14,13 @@ pub enum ShapeDecompressionError {
     WrongDecompressedCount { count: usize, expected: usize },
 }
 
-#[derive(Debug)]
+#[derive(Error, Debug)]
+pub enum UnknownSectionError {
+    #[error("Section [{0}] is known, but could not be parsed")]
+    KnownSection(String),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum EventType {
     Rf,
     Gx,
@@ -19,6 +28,8 @@ pub enum EventType {
     Gz,
     Adc,
     Delay,
+    Trigger,
+    Extension,
 }
 
 impl Display for EventType {
@@ -30,6 +41,8 @@ impl Display for EventType {
             EventType::Gz => "GZ",
             EventType::Adc => "ADC",
             EventType::Delay => "Delay",
+            EventType::Trigger => "Trigger",
+            EventType::Extension => "Extension",
         }
         .fmt(f)
     }
@@ -57,6 +70,76 @@ pub enum ValidationError {
         block_id: u32,
         timing: f64,
     },
+    #[error("{ty} in block #{block_id} exceeds the maximum gradient amplitude: |{amp}| Hz/m > {max} Hz/m")]
+    GradientAmplitude {
+        ty: EventType,
+        block_id: u32,
+        amp: f64,
+        max: f64,
+    },
+    #[error(
+        "{ty} in block #{block_id} exceeds the maximum slew rate: |{slew}| Hz/m/s > {max} Hz/m/s"
+    )]
+    SlewRate {
+        ty: EventType,
+        block_id: u32,
+        slew: f64,
+        max: f64,
+    },
+    #[error(
+        "RF in block #{block_id} starts before the RF dead time: delay {delay}s < {dead_time}s"
+    )]
+    RfDeadTime {
+        block_id: u32,
+        delay: f64,
+        dead_time: f64,
+    },
+    #[error("RF in block #{block_id} leaves no time for the RF ringdown: {remaining}s < {ringdown_time}s")]
+    RfRingdownTime {
+        block_id: u32,
+        remaining: f64,
+        ringdown_time: f64,
+    },
+    #[error(
+        "ADC in block #{block_id} starts before the ADC dead time: delay {delay}s < {dead_time}s"
+    )]
+    AdcDeadTime {
+        block_id: u32,
+        delay: f64,
+        dead_time: f64,
+    },
+    #[error("ADC in block #{block_id} leaves no time for the ADC dead time after it: {remaining}s < {dead_time}s")]
+    AdcDeadTimeAfter {
+        block_id: u32,
+        remaining: f64,
+        dead_time: f64,
+    },
+    #[error("Duration of block #{block_id} is not on the block raster: {duration}s / {raster}s")]
+    BlockNotOnRaster {
+        block_id: u32,
+        duration: f64,
+        raster: f64,
+    },
+    #[error(
+        "{ty} in block #{block_id} has a {timing} that is not on the raster: {value}s / {raster}s"
+    )]
+    EventNotOnRaster {
+        ty: EventType,
+        block_id: u32,
+        /// Name of the timing, e.g. "delay" or "rise"
+        timing: &'static str,
+        value: f64,
+        raster: f64,
+    },
+    #[error("{ty} jumps from {before} Hz/m to {after} Hz/m in block #{block_id} at {time}s")]
+    GradientJump {
+        ty: EventType,
+        block_id: u32,
+        /// Relative to the start of the block
+        time: f64,
+        before: f64,
+        after: f64,
+    },
 }
 
 #[derive(Error, Debug)]
@@ -71,7 +154,7 @@ pub enum ParseError {
     ShapeDecompressionError(#[from] ShapeDecompressionError),
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum SectionType {
     Shapes,
     Delays,
@@ -79,6 +162,7 @@ pub enum SectionType {
     Rfs,
     Gradients,
     Traps,
+    Extensions,
 }
 
 impl Display for SectionType {
@@ -90,6 +174,7 @@ impl Display for SectionType {
             SectionType::Rfs => "Rfs",
             SectionType::Gradients => "Gradients",
             SectionType::Traps => "Traps",
+            SectionType::Extensions => "Extensions",
         }
         .fmt(f)
     }
@@ -103,7 +188,7 @@ pub enum ParseFovError {
     WrongValueCount(usize),
 }
 
-#[derive(Error, Debug)]
+#[derive(Error, Debug, Clone, Copy, PartialEq, Eq)]
 pub enum MissingDefinition {
     #[error(
         "Pulseq since 1.4 mandates time raster definitions, but is GradientRasterTime missing"
@@ -119,12 +204,12 @@ pub enum MissingDefinition {
     BlockDurationRaster,
 }
 
-// TODO: Include shape IDs into shapes for better error reporting
-
 #[derive(Error, Debug)]
 pub enum ConversionError {
     #[error("Expected a single [VERSION] section, found {0}")]
     VersionSectionCount(usize),
+    #[error("Expected at most one [SIGNATURE] section, found {0}")]
+    SignatureSectionCount(usize),
     #[error("{0} Section contains non-unique IDs")]
     EventIdReuse(SectionType),
     #[error("Found re-used IDs between Trap and Gradient events")]
@@ -143,12 +228,245 @@ pub enum ConversionError {
     ShapeNotFound(u32),
     #[error("Can't use 0 as shape index")]
     ShapeIndexZero,
-    #[error("Used a shape of length {shape_len} together with a time shape of length {time_len}")]
-    TimeShapeMismatch { shape_len: usize, time_len: usize },
-    #[error("Used a shape as time shape which contained non-integer values.")]
-    TimeShapeNonInteger,
-    #[error("Used a shape as time shape which is not strictly increasing")]
-    TimeShapeNonIncreasing,
+    #[error("Used shape {shape_id} of length {shape_len} together with time shape {time_id} of length {time_len}")]
+    TimeShapeMismatch {
+        shape_id: u32,
+        time_id: u32,
+        shape_len: usize,
+        time_len: usize,
+    },
+    #[error("Used shape {0} as time shape which contained non-integer values.")]
+    TimeShapeNonInteger(u32),
+    #[error("Used shape {0} as time shape which is not strictly increasing")]
+    TimeShapeNonIncreasing(u32),
+    #[error("Extension #{ref_id} references object {obj_id} of extension type {spec_id}, which does not exist")]
+    BrokenExtensionRef {
+        ref_id: u32,
+        spec_id: u32,
+        obj_id: u32,
+    },
+    #[error("The list of extensions starting at #{0} contains a cycle")]
+    ExtensionCycle(u32),
+    #[error("Invalid data for extension {name}: '{data}'")]
+    InvalidExtensionData { name: String, data: String },
+    #[error("Unknown label '{0}'")]
+    UnknownLabel(String),
+    #[error("Label {0} is a flag and can't be incremented")]
+    IncrementedFlag(LabelName),
+    #[error("Block #{block_id} contains more than one {name} extension")]
+    DuplicateExtension { name: &'static str, block_id: u32 },
+    #[error("Failed to parse {name} extension: {source}")]
+    ExtensionHandler {
+        name: String,
+        source: Box<dyn std::error::Error + Send + Sync>,
+    },
+    #[error("Unknown RF use '{0}'")]
+    UnknownRfUse(char),
+    #[error("line {line}: {error}")]
+    AtLine {
+        line: usize,
+        error: Box<ConversionError>,
+    },
+}
+
+impl ConversionError {
+    /// Attaches the source line of the entry that caused this error.
+    /// Errors that already know their line keep it, 0 means unknown.
+    pub(crate) fn at_line(self, line: usize) -> Self {
+        match self {
+            Self::AtLine { .. } => self,
+            _ if line == 0 => self,
+            _ => Self::AtLine {
+                line,
+                error: Box::new(self),
+            },
+        }
+    }
+
+    /// Removes the source line attached by `at_line`
+    pub(crate) fn without_line(self) -> Self {
+        match self {
+            Self::AtLine { error, .. } => error.without_line(),
+            _ => self,
+        }
+    }
+}
+
+#[derive(Error, Debug)]
+pub enum SignatureError {
+    #[error("The sequence is not signed")]
+    Missing,
+    #[error("Unsupported signature type: {0}")]
+    UnsupportedType(String),
+    #[error("File content hashes to {computed}, but signature is {expected}")]
+    Mismatch { expected: String, computed: String },
+}
+
+/// Returned by the event factories like `make_trapezoid` if the requested
+/// event can't be created within the limits of the `System`
+#[derive(Error, Debug)]
+pub enum MakeError {
+    #[error("Missing parameter, expected {0}")]
+    MissingParameter(&'static str),
+    #[error("Gradient amplitude of {amp} Hz/m exceeds the limit of {max} Hz/m")]
+    GradientAmplitude { amp: f64, max: f64 },
+    #[error("Slew rate of {slew} Hz/m/s exceeds the limit of {max} Hz/m/s")]
+    SlewRate { slew: f64, max: f64 },
+    #[error("An area of {area} 1/m can't be reached within {duration} s")]
+    AreaTooLarge { area: f64, duration: f64 },
+    #[error("Duration of {duration} s is shorter than the ramps ({ramps} s)")]
+    TooShort { duration: f64, ramps: f64 },
+    #[error("{what} of {value} s is not on the raster of {raster} s")]
+    NotOnRaster {
+        what: &'static str,
+        value: f64,
+        raster: f64,
+    },
+    #[error("Invalid waveform: {0}")]
+    InvalidWaveform(&'static str),
+    #[error("The pulse shape integrates to zero and can't be scaled to a flip angle")]
+    ZeroIntegral,
+}
+
+/// A problem that lenient loading recovered from, see `LoadOptions`
+#[derive(Error, Debug)]
+pub enum Warning {
+    #[error("line {line}: Section [{name}] is unknown and was not interpreted")]
+    UnknownSection { name: String, line: usize },
+    #[error("Definition '{0}' is given more than once, using the first value")]
+    DuplicateDefinition(String),
+    #[error("{0}, using the default value")]
+    MissingDefinition(MissingDefinition),
+    #[error("Failed to parse definition '{key} {value}', ignoring it")]
+    InvalidDefinition { key: String, value: String },
+    #[error("{section} Section contains ID {id} more than once, ignoring the later entry{}", line.map_or(String::new(), |line| format!(" in line {line}")))]
+    DuplicateId {
+        section: SectionType,
+        id: u32,
+        line: Option<usize>,
+    },
+    #[error("{}{error}, keeping the {name} extension uninterpreted", line.map_or(String::new(), |line| format!("line {line}: ")))]
+    UninterpretedExtension {
+        name: String,
+        error: ConversionError,
+        line: Option<usize>,
+    },
+    #[error("Sequence validation failed: {0}")]
+    Validation(ValidationError),
+}
+
+impl ValidationError {
+    pub fn block_id(&self) -> u32 {
+        match self {
+            Self::EventTooLong { block_id, .. }
+            | Self::ShapeMismatch { block_id, .. }
+            | Self::NegativeTiming { block_id, .. }
+            | Self::GradientAmplitude { block_id, .. }
+            | Self::SlewRate { block_id, .. }
+            | Self::RfDeadTime { block_id, .. }
+            | Self::RfRingdownTime { block_id, .. }
+            | Self::AdcDeadTime { block_id, .. }
+            | Self::AdcDeadTimeAfter { block_id, .. }
+            | Self::BlockNotOnRaster { block_id, .. }
+            | Self::EventNotOnRaster { block_id, .. }
+            | Self::GradientJump { block_id, .. } => *block_id,
+        }
+    }
+
+    /// The event this error is about, if any
+    pub fn event_type(&self) -> Option<EventType> {
+        match self {
+            Self::EventTooLong { ty, .. }
+            | Self::ShapeMismatch { ty, .. }
+            | Self::NegativeTiming { ty, .. }
+            | Self::GradientAmplitude { ty, .. }
+            | Self::SlewRate { ty, .. }
+            | Self::EventNotOnRaster { ty, .. }
+            | Self::GradientJump { ty, .. } => Some(*ty),
+            Self::RfDeadTime { .. } | Self::RfRingdownTime { .. } => Some(EventType::Rf),
+            Self::AdcDeadTime { .. } | Self::AdcDeadTimeAfter { .. } => Some(EventType::Adc),
+            Self::BlockNotOnRaster { .. } => None,
+        }
+    }
+}
+
+/// Position in a .seq file, rendered like a compiler diagnostic
+#[derive(Debug)]
+pub struct Location {
+    pub path: Option<PathBuf>,
+    /// 1-based
+    pub line: usize,
+    /// 1-based, in characters
+    pub column: usize,
+    /// Number of highlighted characters, starting at `column`
+    pub width: usize,
+    /// The source line
+    pub snippet: String,
+}
+
+impl Location {
+    /// Highlights the whole line, or its n-th whitespace separated field
+    pub(crate) fn new(
+        source: &str,
+        path: Option<&Path>,
+        line: usize,
+        field: Option<usize>,
+    ) -> Self {
+        let snippet = source.lines().nth(line.saturating_sub(1)).unwrap_or("");
+        let snippet = snippet.trim_end().to_owned();
+
+        // (start, len) in characters of all fields
+        let mut fields = Vec::new();
+        let mut start = None;
+        for (i, c) in snippet.chars().chain([' ']).enumerate() {
+            match (c.is_whitespace(), start) {
+                (false, None) => start = Some(i),
+                (true, Some(s)) => {
+                    fields.push((s, i - s));
+                    start = None;
+                }
+                _ => (),
+            }
+        }
+        let whole_line = fields
+            .first()
+            .map_or((0, 0), |&(s, _)| (s, snippet.chars().count() - s));
+        let (start, width) = field
+            .and_then(|field| fields.get(field).copied())
+            .unwrap_or(whole_line);
+
+        Self {
+            path: path.map(Path::to_owned),
+            line,
+            column: start + 1,
+            width: width.max(1),
+            snippet,
+        }
+    }
+}
+
+impl Display for Location {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let gutter = " ".repeat(self.line.to_string().len());
+        match &self.path {
+            Some(path) => writeln!(
+                f,
+                "{gutter}--> {}:{}:{}",
+                path.display(),
+                self.line,
+                self.column
+            )?,
+            None => writeln!(f, "{gutter}--> line {}:{}", self.line, self.column)?,
+        }
+        writeln!(f, "{gutter} |")?;
+        writeln!(f, "{} | {}", self.line, self.snippet)?;
+        write!(
+            f,
+            "{gutter} | {}{}",
+            " ".repeat(self.column - 1),
+            "^".repeat(self.width)
+        )
+    }
 }
 
 #[derive(Error, Debug)]
@@ -159,6 +477,33 @@ pub enum Error {
     ValidationError(#[from] ValidationError),
     #[error("Failed to convert parsed file into sequence: {0}")]
     ConversionError(#[from] ConversionError),
+    #[error("Signature check failed: {0}")]
+    SignatureError(#[from] SignatureError),
     #[error(transparent)]
     IoError(#[from] std::io::Error),
+    #[error("{error}\n{location}")]
+    Located {
+        error: Box<Error>,
+        location: Location,
+    },
+}
+
+impl Error {
+    /// The error without its source location: `Located` errors are
+    /// unwrapped, all others are returned as they are. Use this to match on
+    /// the cause of errors returned by `Sequence::from_source` / `from_file`.
+    pub fn inner(&self) -> &Error {
+        match self {
+            Error::Located { error, .. } => error.inner(),
+            _ => self,
+        }
+    }
+
+    /// Line and snippet of the source that caused this error, if known
+    pub fn location(&self) -> Option<&Location> {
+        match self {
+            Error::Located { location, .. } => Some(location),
+            _ => None,
+        }
+    }
 }
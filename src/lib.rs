@@ -1,7 +1,19 @@
 mod error;
 mod parse_file;
 mod sequence;
+#[cfg(feature = "sim")]
+pub mod sim;
 
-pub use error::Error;
+pub use error::{
+    ConversionError, Error, EventType, Location, MakeError, MissingDefinition, SectionType,
+    ValidationError, Warning,
+};
 pub use parse_file::parse_file;
-pub use sequence::{Adc, Block, Gradient, Rf, Sequence, Shape, TimeRaster};
+pub use sequence::{
+    make_adc, make_arbitrary_grad, make_block_pulse, make_extended_trapezoid, make_gauss_pulse,
+    make_sinc_pulse, make_trapezoid, Adc, Block, Event, Extension, ExtensionHandler,
+    ExtensionHandlers, ExtensionValue, Gradient, KSpace, Label, LabelName, LabelOp, LabelState,
+    LoadOptions, Pulse, PulseSpec, Rf, RfPower, RfUse, Rotation, Sample, Sequence, SequenceBuilder,
+    Shape, SoftDelay, System, TimeRaster, TimedEvent, Timeline, TrapezoidSpec, Trigger,
+    TriggerType, UnknownSection, GAMMA,
+};
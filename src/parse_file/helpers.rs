@@ -72,6 +72,13 @@ pub fn nl() -> Matcher<impl Match> {
     eof() | ((ignore().opt() + eol()).repeat(1..) + ignore().opt())
 }
 
+/// Optional leading whitespace of an entry, returns where the entry starts.
+/// Parsers only see the remaining input, so this is its address, which
+/// `parse_file` turns into a line number once parsing is done.
+pub fn entry_start() -> Parser<impl Parse<Output = usize>> {
+    ws().opt().map(|s| s.as_ptr() as usize)
+}
+
 /// Shorthand for tag + whitespace
 pub fn tag_ws(tag_str: &'static str) -> Matcher<impl Match> {
     tag(tag_str) + ws()
@@ -0,0 +1,94 @@
+// Source lines of the parsed entries. The parsers record where every entry
+// starts (see `helpers::entry_start`) as an address into the source, which
+// is resolved to a line number after parsing succeeded.
+use super::*;
+
+/// Implemented by all parsed entries that know their source line
+pub trait SourceLine {
+    fn line(&self) -> usize;
+    /// ID of the entry within its section
+    fn id(&self) -> u32;
+}
+
+macro_rules! impl_source_line {
+    ($($ty:ty),*) => {
+        $(impl SourceLine for $ty {
+            fn line(&self) -> usize {
+                self.line
+            }
+            fn id(&self) -> u32 {
+                self.id
+            }
+        })*
+    };
+}
+
+impl_source_line!(
+    Block,
+    Rf,
+    Gradient,
+    Trap,
+    Adc,
+    Delay,
+    ExtensionRef,
+    ExtensionSpec,
+    ExtensionObject,
+    Shape
+);
+
+/// Start offsets of all lines of the source
+struct Lines {
+    base: usize,
+    len: usize,
+    starts: Vec<usize>,
+}
+
+impl Lines {
+    fn new(source: &str) -> Self {
+        let starts = std::iter::once(0)
+            .chain(source.match_indices('\n').map(|(i, _)| i + 1))
+            .collect();
+        Self {
+            base: source.as_ptr() as usize,
+            len: source.len(),
+            starts,
+        }
+    }
+
+    /// 1-based line containing the address, 0 if it is not in the source
+    fn line(&self, address: usize) -> usize {
+        match address.checked_sub(self.base) {
+            Some(offset) if offset <= self.len => {
+                self.starts.partition_point(|&start| start <= offset)
+            }
+            _ => 0,
+        }
+    }
+}
+
+/// Replaces the addresses recorded by the parsers with line numbers
+pub fn resolve_lines(source: &str, sections: &mut [Section]) {
+    let lines = Lines::new(source);
+    let resolve = |line: &mut usize| *line = lines.line(*line);
+
+    for section in sections {
+        match section {
+            Section::Blocks(x) => x.iter_mut().for_each(|x| resolve(&mut x.line)),
+            Section::Rfs(x) => x.iter_mut().for_each(|x| resolve(&mut x.line)),
+            Section::Gradients(x) => x.iter_mut().for_each(|x| resolve(&mut x.line)),
+            Section::Traps(x) => x.iter_mut().for_each(|x| resolve(&mut x.line)),
+            Section::Adcs(x) => x.iter_mut().for_each(|x| resolve(&mut x.line)),
+            Section::Delays(x) => x.iter_mut().for_each(|x| resolve(&mut x.line)),
+            Section::Shapes(x) => x.iter_mut().for_each(|x| resolve(&mut x.line)),
+            Section::Extensions(ext) => {
+                ext.refs.iter_mut().for_each(|x| resolve(&mut x.line));
+                for spec in &mut ext.specs {
+                    resolve(&mut spec.line);
+                    spec.instances.iter_mut().for_each(|x| resolve(&mut x.line));
+                }
+            }
+            Section::Unknown { line, .. } => resolve(line),
+            Section::Version(_) | Section::Signature(_) | Section::Definitions(_) => (),
+        }
+    }
+}
@@ -3,9 +3,13 @@ use std::fmt::Display;
 use crate::error;
 
 mod helpers;
+mod locate;
 mod pulseq_1_2;
 mod pulseq_1_3;
 mod pulseq_1_4;
+mod pulseq_1_5;
+
+pub use locate::SourceLine;
 
 // Pulseq is parsed into the following structs, which are modelled after the
 // newest supported pulseq version. Older versions need to convert the data.
@@ -19,6 +23,11 @@ mod pulseq_1_4;
 //       * Rf and Gradient now have an optional time_id for time shapes
 //       * Also added mandatory definitions. Spec defines FOV units to be meters
 //       * Shapes can be compressed
+// v1.5: * Rf now contains the pulse center, frequency and phase offsets in ppm
+//         and a character describing the use of the pulse
+//       * Gradients contain their first and last amplitude
+//       * Adc contains frequency and phase offsets in ppm and a phase shape
+//       * New ROTATIONS and DELAYS (soft delay) extensions
 // vPtx: * Rf extended by two shape IDs for mag and phase shim arrays.
 //         This is allowed in all pulseq versions.
 //         https://gitlab.cs.fau.de/mrzero/pypulseq_rfshim
@@ -27,20 +36,40 @@ pub fn parse_file(source: &str) -> Result<Vec<Section>, error::ParseError> {
     let version = (helpers::nl().opt() + pulseq_1_2::version() + ezpc::none_of("").repeat(0..))
         .parse_all(source)?;
 
-    match version {
+    let mut sections = match version {
         Version {
             major: 1, minor: 2, ..
-        } => Ok(pulseq_1_2::file().parse_all(source)?),
+        } => pulseq_1_2::file().parse_all(source)?,
         Version {
             major: 1, minor: 3, ..
-        } => Ok(pulseq_1_3::file().parse_all(source)?),
+        } => pulseq_1_3::file().parse_all(source)?,
         Version {
             major: 1, minor: 4, ..
-        } => Ok(pulseq_1_4::file().parse_all(source)?),
-        _ => Err(error::ParseError::UnsupportedVersion(version)),
-    }
+        } => pulseq_1_4::file().parse_all(source)?,
+        Version {
+            major: 1, minor: 5, ..
+        } => pulseq_1_5::file().parse_all(source)?,
+        _ => return Err(error::ParseError::UnsupportedVersion(version)),
+    };
+    locate::resolve_lines(source, &mut sections);
+    Ok(sections)
 }
 
+/// Section headers known to the parsers of any supported version
+pub const KNOWN_SECTIONS: [&str; 11] = [
+    "VERSION",
+    "SIGNATURE",
+    "DEFINITIONS",
+    "BLOCKS",
+    "RF",
+    "GRADIENTS",
+    "TRAP",
+    "ADC",
+    "DELAYS",
+    "EXTENSIONS",
+    "SHAPES",
+];
+
 #[derive(Debug)]
 pub enum Section {
     Version(Version),
@@ -54,6 +83,14 @@ pub enum Section {
     Delays(Vec<Delay>),
     Extensions(Extensions),
     Shapes(Vec<Shape>),
+    /// Any other section, e.g. vendor specific or from a newer pulseq version
+    Unknown {
+        name: String,
+        /// Content of the section without empty lines and comments
+        lines: Vec<String>,
+        /// Line of the section header in the source (1-based)
+        line: usize,
+    },
 }
 
 #[derive(Debug)]
@@ -99,6 +136,8 @@ pub struct Block {
     pub gz: u32,
     pub adc: u32,
     pub ext: u32,
+    /// Line in the source (1-based), for error reporting
+    pub line: usize,
 }
 
 #[derive(Debug)]
@@ -109,14 +148,24 @@ pub struct Rf {
     pub mag_id: u32,
     pub phase_id: u32,
     pub time_id: u32,
+    /// `s` (from pulseq: `us`), only given since 1.5
+    pub center: Option<f64>,
     /// `s` (from pulseq: `us`)
     pub delay: f64,
+    /// `ppm`
+    pub freq_ppm: f64,
+    /// `rad/MHz`
+    pub phase_ppm: f64,
     /// `Hz`
     pub freq: f64,
     /// `rad`
     pub phase: f64,
+    /// First letter of the pulse use, `u` (undefined) before 1.5
+    pub rf_use: char,
     /// shim_mag_ID, shim_phase_ID
     pub shim_id: Option<(u32, u32)>,
+    /// Line in the source (1-based), for error reporting
+    pub line: usize,
 }
 
 #[derive(Debug)]
@@ -124,10 +173,16 @@ pub struct Gradient {
     pub id: u32,
     /// `Hz/m`
     pub amp: f64,
+    /// `Hz/m`, only given since 1.5
+    pub first: Option<f64>,
+    /// `Hz/m`, only given since 1.5
+    pub last: Option<f64>,
     pub shape_id: u32,
     pub time_id: u32,
     /// `s` (from pulseq: `us`)
     pub delay: f64,
+    /// Line in the source (1-based), for error reporting
+    pub line: usize,
 }
 
 #[derive(Debug)]
@@ -143,6 +198,8 @@ pub struct Trap {
     pub fall: f64,
     /// `s` (from pulseq: `us`)
     pub delay: f64,
+    /// Line in the source (1-based), for error reporting
+    pub line: usize,
 }
 
 #[derive(Debug)]
@@ -153,10 +210,18 @@ pub struct Adc {
     pub dwell: f64,
     /// `s` (from pulseq: `us`)
     pub delay: f64,
+    /// `ppm`
+    pub freq_ppm: f64,
+    /// `rad/MHz`
+    pub phase_ppm: f64,
     /// `Hz`
     pub freq: f64,
     /// `rad`
     pub phase: f64,
+    /// Shape ID of phase modulation, 0 if not used
+    pub phase_id: u32,
+    /// Line in the source (1-based), for error reporting
+    pub line: usize,
 }
 
 #[derive(Debug)]
@@ -164,6 +229,8 @@ pub struct Delay {
     pub id: u32,
     /// `s` (from pulseq: `us`)
     pub delay: f64,
+    /// Line in the source (1-based), for error reporting
+    pub line: usize,
 }
 
 #[derive(Debug)]
@@ -178,6 +245,8 @@ pub struct ExtensionRef {
     pub spec_id: u32,
     pub obj_id: u32,
     pub next: u32,
+    /// Line in the source (1-based), for error reporting
+    pub line: usize,
 }
 
 #[derive(Debug)]
@@ -185,16 +254,22 @@ pub struct ExtensionSpec {
     pub id: u32,
     pub name: String,
     pub instances: Vec<ExtensionObject>,
+    /// Line in the source (1-based), for error reporting
+    pub line: usize,
 }
 
 #[derive(Debug)]
 pub struct ExtensionObject {
     pub id: u32,
     pub data: String,
+    /// Line in the source (1-based), for error reporting
+    pub line: usize,
 }
 
 #[derive(Debug)]
 pub struct Shape {
     pub id: u32,
     pub samples: Vec<f64>,
+    /// Line in the source (1-based), for error reporting
+    pub line: usize,
 }
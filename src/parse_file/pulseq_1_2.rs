@@ -12,7 +12,8 @@ pub fn file() -> Parser<impl Parse<Output = Vec<Section>>> {
             | traps().map(Section::Traps)
             | adcs().map(Section::Adcs)
             | delays().map(Section::Delays)
-            | shapes().map(Section::Shapes))
+            | shapes().map(Section::Shapes)
+            | unknown())
         .repeat(0..)
 }
 
@@ -31,30 +32,55 @@ pub fn version() -> Parser<impl Parse<Output = Version>> {
     )
 }
 
+/// Captures any section that is not known, so that the rest of the file can
+/// still be parsed. Known sections that failed to parse are not captured.
+pub fn unknown() -> Parser<impl Parse<Output = Section>> {
+    let name = (tag("[") + none_of("]\n").repeat(1..).map(|s| s.to_owned()) + tag("]") + nl())
+        .convert(
+            |name| {
+                if KNOWN_SECTIONS.contains(&name.as_str()) {
+                    Err(error::UnknownSectionError::KnownSection(name))
+                } else {
+                    Ok(name)
+                }
+            },
+            "Failed to parse known section",
+        );
+    let line = (none_of("[\n") + none_of("\n").repeat(0..)).map(|s| s.trim().to_owned()) + nl();
+
+    (entry_start() + name + line.repeat(0..)).map(|((line, name), lines)| Section::Unknown {
+        name,
+        lines,
+        line,
+    })
+}
+
 pub fn definitions() -> Parser<impl Parse<Output = Vec<(String, String)>>> {
     let def = ident() + ws() + none_of("\n").repeat(0..).map(|s| s.trim().to_owned()) + nl();
     tag_nl("[DEFINITIONS]") + def.repeat(0..)
 }
 
 pub fn blocks() -> Parser<impl Parse<Output = Vec<Block>>> {
-    let block = (ws().opt() + int() + (ws() + int()).repeat(6)).map(|(id, tags)| Block {
-        id,
-        dur: BlockDuration::DelayId(tags[0]),
-        rf: tags[1],
-        gx: tags[2],
-        gy: tags[3],
-        gz: tags[4],
-        adc: tags[5],
-        ext: 0,
-    });
+    let block =
+        (entry_start() + int() + (ws() + int()).repeat(6)).map(|((line, id), tags)| Block {
+            id,
+            dur: BlockDuration::DelayId(tags[0]),
+            rf: tags[1],
+            gx: tags[2],
+            gy: tags[3],
+            gz: tags[4],
+            adc: tags[5],
+            ext: 0,
+            line,
+        });
     tag_nl("[BLOCKS]") + (block + nl()).repeat(0..)
 }
 
 pub fn rfs() -> Parser<impl Parse<Output = Vec<Rf>>> {
     let i = || ws() + int();
     let f = || ws() + float();
-    let rf = (ws().opt() + int() + f() + i() + i() + i() + f() + f() + (i() + i()).opt()).map(
-        |(((((((id, amp), mag_id), phase_id), delay), freq), phase), shim_id_raw)| {
+    let rf = (entry_start() + int() + f() + i() + i() + i() + f() + f() + (i() + i()).opt()).map(
+        |((((((((line, id), amp), mag_id), phase_id), delay), freq), phase), shim_id_raw)| {
             // Shim indices of 0, 0 are treated as no shim - 0 is an invalid shape_id
             let shim_id = match shim_id_raw {
                 Some((0, 0)) => None,
@@ -66,10 +92,15 @@ pub fn rfs() -> Parser<impl Parse<Output = Vec<Rf>>> {
                 mag_id,
                 phase_id,
                 time_id: 0,
+                center: None,
                 delay: delay as f64 * 1e-6,
+                freq_ppm: 0.0,
+                phase_ppm: 0.0,
                 freq,
                 phase,
+                rf_use: 'u',
                 shim_id,
+                line,
             }
         },
     );
@@ -80,12 +111,17 @@ pub fn gradients() -> Parser<impl Parse<Output = Vec<Gradient>>> {
     let i = || ws() + int();
     let f = ws() + float();
     let grad =
-        (ws().opt() + int() + f + i() + i()).map(|(((id, amp), shape_id), delay)| Gradient {
-            id,
-            amp,
-            shape_id,
-            time_id: 0,
-            delay: delay as f64 * 1e-6,
+        (entry_start() + int() + f + i() + i()).map(|((((line, id), amp), shape_id), delay)| {
+            Gradient {
+                id,
+                amp,
+                first: None,
+                last: None,
+                shape_id,
+                time_id: 0,
+                delay: delay as f64 * 1e-6,
+                line,
+            }
         });
     tag_nl("[GRADIENTS]") + (grad + nl()).repeat(0..)
 }
@@ -93,14 +129,15 @@ pub fn gradients() -> Parser<impl Parse<Output = Vec<Gradient>>> {
 pub fn traps() -> Parser<impl Parse<Output = Vec<Trap>>> {
     let i = || ws() + int();
     let f = ws() + float();
-    let trap = (ws().opt() + int() + f + i() + i() + i() + i()).map(
-        |(((((id, amp), rise), flat), fall), delay)| Trap {
+    let trap = (entry_start() + int() + f + i() + i() + i() + i()).map(
+        |((((((line, id), amp), rise), flat), fall), delay)| Trap {
             id,
             amp,
             rise: rise as f64 * 1e-6,
             flat: flat as f64 * 1e-6,
             fall: fall as f64 * 1e-6,
             delay: delay as f64 * 1e-6,
+            line,
         },
     );
     tag_nl("[TRAP]") + (trap + nl()).repeat(0..)
@@ -109,23 +146,28 @@ pub fn traps() -> Parser<impl Parse<Output = Vec<Trap>>> {
 pub fn adcs() -> Parser<impl Parse<Output = Vec<Adc>>> {
     let i = || ws() + int();
     let f = || ws() + float();
-    let adc = (ws().opt() + int() + i() + f() + i() + f() + f()).map(
-        |(((((id, num), dwell), delay), freq), phase)| Adc {
+    let adc = (entry_start() + int() + i() + f() + i() + f() + f()).map(
+        |((((((line, id), num), dwell), delay), freq), phase)| Adc {
             id,
             num,
             dwell: dwell * 1e-9,
             delay: delay as f64 * 1e-6,
+            freq_ppm: 0.0,
+            phase_ppm: 0.0,
             freq,
             phase,
+            phase_id: 0,
+            line,
         },
     );
     tag_nl("[ADC]") + (adc + nl()).repeat(0..)
 }
 
 pub fn delays() -> Parser<impl Parse<Output = Vec<Delay>>> {
-    let delay = (ws().opt() + int() + ws() + float()).map(|(id, delay)| Delay {
+    let delay = (entry_start() + int() + ws() + float()).map(|((line, id), delay)| Delay {
         id,
         delay: delay * 1e-6,
+        line,
     });
     tag_nl("[DELAYS]") + (delay + nl()).repeat(0..)
 }
@@ -141,12 +183,12 @@ pub fn raw_shape() -> Parser<impl Parse<Output = (u32, (u32, Vec<f64>))>> {
 pub fn shapes() -> Parser<impl Parse<Output = Vec<Shape>>> {
     // The spec says optional RLE only since version 1.4 but seems to be used earlier.
     // We allow it in all versions unless there is a bug report for failing decompression.
-    let shape = raw_shape().convert(
-        |(id, (num_samples, samples))| {
+    let shape = (entry_start() + raw_shape()).convert(
+        |(line, (id, (num_samples, samples)))| {
             if samples.len() == num_samples as usize {
-                Ok(Shape { id, samples })
+                Ok(Shape { id, samples, line })
             } else {
-                decompress_shape(samples, num_samples).map(|samples| Shape { id, samples })
+                decompress_shape(samples, num_samples).map(|samples| Shape { id, samples, line })
             }
         },
         "Failed to decompress shape",
@@ -1,6 +1,8 @@
 use ezpc::*;
 
-use super::pulseq_1_2::{adcs, definitions, delays, gradients, rfs, shapes, traps, version};
+use super::pulseq_1_2::{
+    adcs, definitions, delays, gradients, rfs, shapes, traps, unknown, version,
+};
 use super::{helpers::*, *};
 
 pub fn file() -> Parser<impl Parse<Output = Vec<Section>>> {
@@ -14,45 +16,49 @@ pub fn file() -> Parser<impl Parse<Output = Vec<Section>>> {
             | adcs().map(Section::Adcs)
             | delays().map(Section::Delays)
             | extensions().map(Section::Extensions)
-            | shapes().map(Section::Shapes))
+            | shapes().map(Section::Shapes)
+            | unknown())
         .repeat(0..)
 }
 
 fn blocks() -> Parser<impl Parse<Output = Vec<Block>>> {
-    let block = (ws().opt() + int() + (ws() + int()).repeat(7)).map(|(id, tags)| Block {
-        id,
-        dur: BlockDuration::DelayId(tags[0]),
-        rf: tags[1],
-        gx: tags[2],
-        gy: tags[3],
-        gz: tags[4],
-        adc: tags[5],
-        ext: tags[6],
-    });
+    let block =
+        (entry_start() + int() + (ws() + int()).repeat(7)).map(|((line, id), tags)| Block {
+            id,
+            dur: BlockDuration::DelayId(tags[0]),
+            rf: tags[1],
+            gx: tags[2],
+            gy: tags[3],
+            gz: tags[4],
+            adc: tags[5],
+            ext: tags[6],
+            line,
+        });
     tag_nl("[BLOCKS]") + (block + nl()).repeat(0..)
 }
 
 pub fn extensions() -> Parser<impl Parse<Output = Extensions>> {
     let i = || ws() + int();
-    let ext_ref =
-        (ws().opt() + int() + i() + i() + i() + nl()).map(|(((id, spec_id), obj_id), next)| {
-            ExtensionRef {
-                id,
-                spec_id,
-                obj_id,
-                next,
-            }
-        });
-    let rest_of_line = none_of("\n").repeat(1..).map(|s| s.trim().to_owned());
-    let ext_obj =
-        (ws().opt() + int() + rest_of_line + nl()).map(|(id, data)| ExtensionObject { id, data });
-    let ext_spec = (tag_ws("extension") + ident() + ws() + int() + nl() + ext_obj.repeat(1..)).map(
-        |((name, id), instances)| ExtensionSpec {
+    let ext_ref = (entry_start() + int() + i() + i() + i() + nl()).map(
+        |((((line, id), spec_id), obj_id), next)| ExtensionRef {
             id,
-            name,
-            instances,
+            spec_id,
+            obj_id,
+            next,
+            line,
         },
     );
+    let rest_of_line = none_of("\n").repeat(1..).map(|s| s.trim().to_owned());
+    let ext_obj = (entry_start() + int() + rest_of_line + nl())
+        .map(|((line, id), data)| ExtensionObject { id, data, line });
+    let ext_spec =
+        (entry_start() + tag_ws("extension") + ident() + ws() + int() + nl() + ext_obj.repeat(1..))
+            .map(|(((line, name), id), instances)| ExtensionSpec {
+                id,
+                name,
+                instances,
+                line,
+            });
     (tag_nl("[EXTENSIONS]") + ext_ref.repeat(0..) + ext_spec.repeat(0..))
         .map(|(refs, specs)| Extensions { refs, specs })
 }
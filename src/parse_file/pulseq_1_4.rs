@@ -1,6 +1,6 @@
 use ezpc::*;
 
-use super::pulseq_1_2::{adcs, definitions, shapes, traps, version};
+use super::pulseq_1_2::{adcs, definitions, shapes, traps, unknown, version};
 use super::pulseq_1_3::extensions;
 use super::{helpers::*, *};
 
@@ -15,7 +15,8 @@ pub fn file() -> Parser<impl Parse<Output = Vec<Section>>> {
             | traps().map(Section::Traps)
             | adcs().map(Section::Adcs)
             | extensions().map(Section::Extensions)
-            | shapes().map(Section::Shapes))
+            | shapes().map(Section::Shapes)
+            | unknown())
         .repeat(0..)
 }
 
@@ -31,25 +32,30 @@ pub fn signature() -> Parser<impl Parse<Output = Signature>> {
 }
 
 pub fn blocks() -> Parser<impl Parse<Output = Vec<Block>>> {
-    let block = (ws().opt() + int() + (ws() + int()).repeat(7)).map(|(id, tags)| Block {
-        id,
-        dur: BlockDuration::Duration(tags[0]),
-        rf: tags[1],
-        gx: tags[2],
-        gy: tags[3],
-        gz: tags[4],
-        adc: tags[5],
-        ext: tags[6],
-    });
+    let block =
+        (entry_start() + int() + (ws() + int()).repeat(7)).map(|((line, id), tags)| Block {
+            id,
+            dur: BlockDuration::Duration(tags[0]),
+            rf: tags[1],
+            gx: tags[2],
+            gy: tags[3],
+            gz: tags[4],
+            adc: tags[5],
+            ext: tags[6],
+            line,
+        });
     tag_nl("[BLOCKS]") + (block + nl()).repeat(0..)
 }
 
 pub fn rfs() -> Parser<impl Parse<Output = Vec<Rf>>> {
     let i = || ws() + int();
     let f = || ws() + float();
-    let rf = (ws().opt() + int() + f() + i() + i() + i() + i() + f() + f() + (i() + i()).opt())
+    let rf = (entry_start() + int() + f() + i() + i() + i() + i() + f() + f() + (i() + i()).opt())
         .map(
-            |((((((((id, amp), mag_id), phase_id), time_id), delay), freq), phase), shim_id_raw)| {
+            |(
+                ((((((((line, id), amp), mag_id), phase_id), time_id), delay), freq), phase),
+                shim_id_raw,
+            )| {
                 // Shim indices of 0, 0 are treated as no shim - 0 is an invalid shape_id
                 let shim_id = match shim_id_raw {
                     Some((0, 0)) => None,
@@ -61,10 +67,15 @@ pub fn rfs() -> Parser<impl Parse<Output = Vec<Rf>>> {
                     mag_id,
                     phase_id,
                     time_id,
+                    center: None,
                     delay: delay as f64 * 1e-6,
+                    freq_ppm: 0.0,
+                    phase_ppm: 0.0,
                     freq,
                     phase,
+                    rf_use: 'u',
                     shim_id,
+                    line,
                 }
             },
         );
@@ -74,13 +85,16 @@ pub fn rfs() -> Parser<impl Parse<Output = Vec<Rf>>> {
 pub fn gradients() -> Parser<impl Parse<Output = Vec<Gradient>>> {
     let i = || ws() + int();
     let f = ws() + float();
-    let grad = (ws().opt() + int() + f + i() + i() + i()).map(
-        |((((id, amp), shape_id), time_id), delay)| Gradient {
+    let grad = (entry_start() + int() + f + i() + i() + i()).map(
+        |(((((line, id), amp), shape_id), time_id), delay)| Gradient {
             id,
             amp,
+            first: None,
+            last: None,
             shape_id,
             time_id,
             delay: delay as f64 * 1e-6,
+            line,
         },
     );
     tag_nl("[GRADIENTS]") + (grad + nl()).repeat(0..)
@@ -0,0 +1,97 @@
+use ezpc::*;
+
+use super::pulseq_1_2::{definitions, shapes, traps, unknown, version};
+use super::pulseq_1_3::extensions;
+use super::pulseq_1_4::{blocks, signature};
+use super::{helpers::*, *};
+
+pub fn file() -> Parser<impl Parse<Output = Vec<Section>>> {
+    nl().opt()
+        + (version().map(Section::Version)
+            | signature().map(Section::Signature)
+            | definitions().map(Section::Definitions)
+            | blocks().map(Section::Blocks)
+            | rfs().map(Section::Rfs)
+            | gradients().map(Section::Gradients)
+            | traps().map(Section::Traps)
+            | adcs().map(Section::Adcs)
+            | extensions().map(Section::Extensions)
+            | shapes().map(Section::Shapes)
+            | unknown())
+        .repeat(0..)
+}
+
+fn rfs() -> Parser<impl Parse<Output = Vec<Rf>>> {
+    let i = || ws() + int();
+    let f = || ws() + float();
+    let rf_use = ws() + one_of("erispou").map(|s| s.chars().next().unwrap());
+    // Split into groups to keep the nesting of the output tuple manageable
+    let shape_ids = i() + i() + i();
+    let offsets = f() + f() + f() + f();
+    let rf = (entry_start() + int() + f() + shape_ids + f() + i() + offsets + rf_use).map(
+        |(
+            ((((((line, id), amp), ((mag_id, phase_id), time_id)), center), delay), offsets),
+            rf_use,
+        )| {
+            let (((freq_ppm, phase_ppm), freq), phase) = offsets;
+            Rf {
+                id,
+                amp,
+                mag_id,
+                phase_id,
+                time_id,
+                center: Some(center * 1e-6),
+                delay: delay as f64 * 1e-6,
+                freq_ppm,
+                phase_ppm,
+                freq,
+                phase,
+                rf_use,
+                shim_id: None,
+                line,
+            }
+        },
+    );
+    tag_nl("[RF]") + (rf + nl()).repeat(0..)
+}
+
+fn gradients() -> Parser<impl Parse<Output = Vec<Gradient>>> {
+    let i = || ws() + int();
+    let f = || ws() + float();
+    let grad = (entry_start() + int() + f() + f() + f() + i() + i() + i()).map(
+        |(((((((line, id), amp), first), last), shape_id), time_id), delay)| Gradient {
+            id,
+            amp,
+            first: Some(first),
+            last: Some(last),
+            shape_id,
+            time_id,
+            delay: delay as f64 * 1e-6,
+            line,
+        },
+    );
+    tag_nl("[GRADIENTS]") + (grad + nl()).repeat(0..)
+}
+
+fn adcs() -> Parser<impl Parse<Output = Vec<Adc>>> {
+    let i = || ws() + int();
+    let f = || ws() + float();
+    let adc = (entry_start() + int() + i() + f() + i() + f() + f() + f() + f() + i()).map(
+        |(
+            ((((((((line, id), num), dwell), delay), freq_ppm), phase_ppm), freq), phase),
+            phase_id,
+        )| Adc {
+            id,
+            num,
+            dwell: dwell * 1e-9,
+            delay: delay as f64 * 1e-6,
+            freq_ppm,
+            phase_ppm,
+            freq,
+            phase,
+            phase_id,
+            line,
+        },
+    );
+    tag_nl("[ADC]") + (adc + nl()).repeat(0..)
+}
@@ -0,0 +1,209 @@
+// Construction of sequences in Rust, as an alternative to loading .seq files
+use std::f64::consts::TAU;
+
+use super::*;
+
+/// Builds a sequence block by block. Blocks are numbered in the order they
+/// are added, their durations are rounded up to the block raster.
+pub struct SequenceBuilder {
+    time_raster: TimeRaster,
+    name: Option<String>,
+    fov: Option<(f64, f64, f64)>,
+    definitions: HashMap<String, String>,
+    blocks: Vec<Block>,
+}
+
+impl SequenceBuilder {
+    pub fn new(time_raster: TimeRaster) -> Self {
+        Self {
+            time_raster,
+            name: None,
+            fov: None,
+            definitions: HashMap::new(),
+            blocks: Vec::new(),
+        }
+    }
+
+    pub fn name(mut self, name: impl Into<String>) -> Self {
+        self.name = Some(name.into());
+        self
+    }
+
+    /// Unit: `[m]`
+    pub fn fov(mut self, x: f64, y: f64, z: f64) -> Self {
+        self.fov = Some((x, y, z));
+        self
+    }
+
+    /// Adds a custom definition, written into the [DEFINITIONS] section
+    pub fn definition(mut self, key: impl Into<String>, value: impl Into<String>) -> Self {
+        self.definitions.insert(key.into(), value.into());
+        self
+    }
+
+    pub fn time_raster(&self) -> &TimeRaster {
+        &self.time_raster
+    }
+
+    /// Appends the block and returns its ID. The ID is the position of the
+    /// block, starting with 1. The block lasts at least `block.duration` and
+    /// until its longest event ended, rounded up to the block raster.
+    /// Events can be shared between blocks by adding the same `Arc`.
+    pub fn add_block(&mut self, mut block: Block) -> u32 {
+        let duration = block.duration.max(block.events_duration(&self.time_raster));
+        let raster = self.time_raster.block;
+        // Same tolerance as used by the timeline
+        block.duration = (duration / raster - 1e-6).ceil().max(0.0) * raster;
+        let id = self.blocks.len() as u32 + 1;
+        block.id = id;
+
+        self.blocks.push(block);
+        id
+    }
+
+    /// Appends an empty block with the given duration, unit: `[s]`
+    pub fn add_delay(&mut self, duration: f64) -> u32 {
+        self.add_block(Block {
+            duration,
+            ..Default::default()
+        })
+    }
+
+    /// Returns the sequence if it passes `Sequence::validate`
+    pub fn build(self) -> Result<Sequence, ValidationError> {
+        let seq = Sequence {
+            time_raster: self.time_raster,
+            name: self.name,
+            fov: self.fov,
+            definitions: self.definitions,
+            blocks: self.blocks,
+            signature: None,
+            unknown_sections: Vec::new(),
+        };
+        seq.validate()?;
+        Ok(seq)
+    }
+}
+
+impl Block {
+    /// Time until all events of the block ended, including their delays.
+    /// Unit: `[s]`
+    pub fn events_duration(&self, time_raster: &TimeRaster) -> f64 {
+        [
+            self.rf.as_ref().map(|rf| rf.duration(time_raster.rf)),
+            self.gx.as_ref().map(|gx| gx.duration(time_raster.grad)),
+            self.gy.as_ref().map(|gy| gy.duration(time_raster.grad)),
+            self.gz.as_ref().map(|gz| gz.duration(time_raster.grad)),
+            self.adc.as_ref().map(|adc| adc.duration()),
+        ]
+        .into_iter()
+        .flatten()
+        .chain(self.triggers.iter().map(|t| t.total_duration()))
+        .fold(0.0, f64::max)
+    }
+}
+
+impl Shape {
+    pub fn new(samples: Vec<f64>) -> Self {
+        Self {
+            samples,
+            id: None,
+            time_id: None,
+        }
+    }
+}
+
+impl Rf {
+    /// Creates a pulse from its complex waveform `(re, im)` in `[Hz]`,
+    /// sampled on the RF raster. Like pypulseq, it is stored as the peak
+    /// amplitude and magnitude and phase shapes; all other properties are
+    /// zero or undefined and can be set afterwards.
+    pub fn from_complex(signal: &[(f64, f64)], delay: f64) -> Self {
+        let amp = signal
+            .iter()
+            .fold(0.0f64, |max, (re, im)| max.max(re.hypot(*im)));
+        let mag = signal
+            .iter()
+            .map(|(re, im)| if amp > 0.0 { re.hypot(*im) / amp } else { 0.0 })
+            .collect();
+        // Phase shapes are stored in cycles: [0, 1) instead of [0, 2pi)
+        let phase = signal
+            .iter()
+            .map(|(re, im)| im.atan2(*re).rem_euclid(TAU) / TAU)
+            .collect();
+
+        Self {
+            id: None,
+            amp,
+            phase: 0.0,
+            delay,
+            freq: 0.0,
+            freq_ppm: 0.0,
+            phase_ppm: 0.0,
+            center: None,
+            usage: RfUse::Undefined,
+            amp_shape: Arc::new(Shape::new(mag)),
+            phase_shape: Arc::new(Shape::new(phase)),
+            shim_shape: None,
+        }
+    }
+
+    /// Creates a pulse from a real waveform in `[Hz]`, see `from_complex`.
+    /// Negative samples are stored with a phase of pi.
+    pub fn from_real(signal: &[f64], delay: f64) -> Self {
+        let signal: Vec<_> = signal.iter().map(|&x| (x, 0.0)).collect();
+        Self::from_complex(&signal, delay)
+    }
+}
+
+impl Gradient {
+    /// Unit of `amp`: `[Hz/m]`, all timings: `[s]`
+    pub fn trap(amp: f64, rise: f64, flat: f64, fall: f64, delay: f64) -> Self {
+        Self::Trap {
+            id: None,
+            amp,
+            rise,
+            flat,
+            fall,
+            delay,
+        }
+    }
+
+    /// Creates a free gradient from its waveform in `[Hz/m]`, sampled on
+    /// the gradient raster. It is stored as peak amplitude and a normalized
+    /// shape; the first and last amplitude are left undefined.
+    pub fn free(waveform: &[f64], delay: f64) -> Self {
+        let amp = waveform.iter().fold(0.0f64, |max, x| max.max(x.abs()));
+        let shape = waveform
+            .iter()
+            .map(|x| if amp > 0.0 { x / amp } else { 0.0 })
+            .collect();
+
+        Self::Free {
+            id: None,
+            amp,
+            first: None,
+            last: None,
+            delay,
+            shape: Arc::new(Shape::new(shape)),
+        }
+    }
+}
+
+impl Adc {
+    /// ADC with `num` samples, all other properties are zero.
+    /// Unit of `dwell` and `delay`: `[s]`
+    pub fn new(num: u32, dwell: f64, delay: f64) -> Self {
+        Self {
+            id: None,
+            num,
+            dwell,
+            delay,
+            freq: 0.0,
+            phase: 0.0,
+            freq_ppm: 0.0,
+            phase_ppm: 0.0,
+            phase_shape: None,
+        }
+    }
+}
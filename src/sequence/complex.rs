@@ -0,0 +1,56 @@
+// Minimal complex arithmetic for signal and spectrum calculations
+
+/// Complex number, kept private to avoid a dependency for a few operations
+#[derive(Debug, Clone, Copy, Default)]
+pub(crate) struct Complex {
+    pub re: f64,
+    pub im: f64,
+}
+
+impl Complex {
+    pub fn polar(abs: f64, arg: f64) -> Self {
+        let (sin, cos) = arg.sin_cos();
+        Self {
+            re: abs * cos,
+            im: abs * sin,
+        }
+    }
+
+    pub fn abs(self) -> f64 {
+        self.re.hypot(self.im)
+    }
+
+    #[cfg_attr(not(feature = "sim"), allow(dead_code))]
+    pub fn arg(self) -> f64 {
+        self.im.atan2(self.re)
+    }
+
+    #[cfg_attr(not(feature = "sim"), allow(dead_code))]
+    pub fn conj(self) -> Self {
+        Self {
+            re: self.re,
+            im: -self.im,
+        }
+    }
+
+    pub fn scale(self, x: f64) -> Self {
+        Self {
+            re: self.re * x,
+            im: self.im * x,
+        }
+    }
+
+    pub fn add(self, other: Self) -> Self {
+        Self {
+            re: self.re + other.re,
+            im: self.im + other.im,
+        }
+    }
+
+    pub fn mul(self, other: Self) -> Self {
+        Self {
+            re: self.re * other.re - self.im * other.im,
+            im: self.re * other.im + self.im * other.re,
+        }
+    }
+}
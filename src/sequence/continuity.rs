@@ -0,0 +1,77 @@
+// Validation of gradient continuity, within and across blocks
+use super::*;
+
+impl Sequence {
+    /// Checks that no gradient channel jumps by more than `threshold` (in
+    /// `[Hz/m]`), which would require an infinite slew rate. Gradients must
+    /// start and end at zero unless they touch the block boundary, where the
+    /// neighbouring block has to continue with the same amplitude.
+    /// The sequence starts and ends with all gradients at zero. Channels are
+    /// checked as stored in the file, rotations are not applied.
+    pub fn check_continuity(&self, threshold: f64) -> Vec<ValidationError> {
+        let raster = self.time_raster.grad;
+        // Timings closer than this are considered equal
+        let eps = raster * 1e-3;
+        let mut errors = Vec::new();
+
+        for ty in [EventType::Gx, EventType::Gy, EventType::Gz] {
+            // Amplitude at the end of the previous block
+            let mut prev = 0.0;
+
+            for block in &self.blocks {
+                let mut check = |time: f64, before: f64, after: f64| {
+                    if (after - before).abs() > threshold {
+                        errors.push(ValidationError::GradientJump {
+                            ty,
+                            block_id: block.id,
+                            time,
+                            before,
+                            after,
+                        });
+                    }
+                };
+
+                let grad = match ty {
+                    EventType::Gx => &block.gx,
+                    EventType::Gy => &block.gy,
+                    _ => &block.gz,
+                };
+                let Some(grad) = grad else {
+                    check(0.0, prev, 0.0);
+                    prev = 0.0;
+                    continue;
+                };
+
+                let delay = grad.delay();
+                if delay > eps {
+                    check(0.0, prev, 0.0);
+                    check(delay, 0.0, grad.first());
+                } else {
+                    check(0.0, prev, grad.first());
+                }
+
+                let end = grad.duration(raster);
+                if end < block.duration - eps {
+                    check(end, grad.last(), 0.0);
+                    prev = 0.0;
+                } else {
+                    prev = grad.last();
+                }
+            }
+
+            if let Some(block) = self.blocks.last() {
+                if prev.abs() > threshold {
+                    errors.push(ValidationError::GradientJump {
+                        ty,
+                        block_id: block.id,
+                        time: block.duration,
+                        before: prev,
+                        after: 0.0,
+                    });
+                }
+            }
+        }
+
+        errors
+    }
+}
@@ -34,9 +34,18 @@ impl Display for Sequence {
         let mut rf_refs = RefPrinter::new();
         let mut grad_refs = RefPrinter::new();
         let mut adc_refs = RefPrinter::new();
+        let mut trigger_refs = RefPrinter::new();
+        let mut ext_refs = RefPrinter::new();
 
         for block in &self.blocks {
-            block.fmt(f, &mut rf_refs, &mut grad_refs, &mut adc_refs)?;
+            block.fmt(
+                f,
+                &mut rf_refs,
+                &mut grad_refs,
+                &mut adc_refs,
+                &mut trigger_refs,
+                &mut ext_refs,
+            )?;
         }
 
         let mut shape_refs = RefPrinter::new();
@@ -69,6 +78,17 @@ impl Display for Sequence {
         writeln!(f, "#               [us]     [ms]     [Hz]    [rad]")?;
         writeln!(f, "{adc_refs}")?;
 
+        writeln!(f, "\nTRIGGERS")?;
+        writeln!(f, "--------")?;
+        writeln!(f, "#  ID   type channel    delay duration")?;
+        writeln!(f, "#                        [ms]     [ms]")?;
+        writeln!(f, "{trigger_refs}")?;
+
+        writeln!(f, "\nEXTENSIONS")?;
+        writeln!(f, "----------")?;
+        writeln!(f, "#  ID name data")?;
+        writeln!(f, "{ext_refs}")?;
+
         writeln!(f, "\nSHAPES")?;
         writeln!(f, "------")?;
         writeln!(f, "#  ID     num")?;
@@ -96,6 +116,8 @@ impl Block {
         rf_refs: &mut RefPrinter<Rf>,
         grad_refs: &mut RefPrinter<Gradient>,
         adc_refs: &mut RefPrinter<Adc>,
+        trigger_refs: &mut RefPrinter<Trigger>,
+        ext_refs: &mut RefPrinter<Extension>,
     ) -> std::fmt::Result {
         write!(f, "[{:4}] ", self.id)?;
 
@@ -105,7 +127,38 @@ impl Block {
         write!(f, "{}) ", grad_refs.print_opt(&self.gz))?;
         write!(f, "{} ", adc_refs.print_opt(&self.adc))?;
 
-        writeln!(f, "| {} ms", self.duration * 1e3)
+        write!(f, "| {} ms", self.duration * 1e3)?;
+
+        if !self.triggers.is_empty() {
+            let triggers: Vec<_> = self
+                .triggers
+                .iter()
+                .map(|t| trigger_refs.print(t))
+                .collect();
+            write!(f, " | trig [{}]", triggers.join(","))?;
+        }
+        if let Some(rot) = &self.rotation {
+            write!(f, " | rot {:?}", rot.quaternion)?;
+        }
+        if let Some(delay) = &self.soft_delay {
+            write!(f, " | soft delay '{}' #{}", delay.hint, delay.num)?;
+        }
+        if !self.labels.is_empty() {
+            let labels: Vec<_> = self
+                .labels
+                .iter()
+                .map(|l| match l.op {
+                    LabelOp::Set => format!("{}={}", l.name, l.value),
+                    LabelOp::Inc => format!("{}+={}", l.name, l.value),
+                })
+                .collect();
+            write!(f, " | labels [{}]", labels.join(", "))?;
+        }
+        if !self.extensions.is_empty() {
+            let exts: Vec<_> = self.extensions.iter().map(|e| ext_refs.print(e)).collect();
+            write!(f, " | ext [{}]", exts.join(","))?;
+        }
+        writeln!(f)
     }
 }
 
@@ -182,13 +235,49 @@ impl Display for RefPrinter<Adc> {
     }
 }
 
+impl Display for RefPrinter<Trigger> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let mut tmp: Vec<_> = self.0.iter().map(|(_addr, (rc, id))| (rc, *id)).collect();
+        tmp.sort_by_key(|(_, id)| *id);
+
+        for (rc, id) in tmp {
+            let ty = match rc.ty {
+                TriggerType::Physio => "physio",
+                TriggerType::Output => "output",
+            };
+            writeln!(
+                f,
+                "[{id:4}] {ty:>6} {:7} {:8.3} {:8.3}",
+                rc.channel,
+                rc.delay * 1e3,
+                rc.duration * 1e3,
+            )?;
+        }
+
+        Ok(())
+    }
+}
+
+impl Display for RefPrinter<Extension> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let mut tmp: Vec<_> = self.0.iter().map(|(_addr, (rc, id))| (rc, *id)).collect();
+        tmp.sort_by_key(|(_, id)| *id);
+
+        for (rc, id) in tmp {
+            writeln!(f, "[{id:4}] {} {}", rc.name, rc.data)?;
+        }
+
+        Ok(())
+    }
+}
+
 impl Display for RefPrinter<Shape> {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         let mut tmp: Vec<_> = self.0.iter().map(|(_addr, (rc, id))| (rc, *id)).collect();
         tmp.sort_by_key(|(_, id)| *id);
 
         for (rc, id) in tmp {
-            writeln!(f, "[{id:4}] {:6}", rc.0.len())?;
+            writeln!(f, "[{id:4}] {:6}", rc.samples.len())?;
         }
 
         Ok(())
@@ -207,7 +296,9 @@ impl RefPrinter<Gradient> {
         for (rc, id) in tmp {
             write!(f, "[{id:4}] ")?;
             match rc.as_ref() {
-                Gradient::Free { amp, shape, delay } => writeln!(
+                Gradient::Free {
+                    amp, shape, delay, ..
+                } => writeln!(
                     f,
                     "F {:8.3} {:8.3} {{{}}}",
                     delay * 1e3,
@@ -220,6 +311,7 @@ impl RefPrinter<Gradient> {
                     flat,
                     fall,
                     delay,
+                    ..
                 } => writeln!(
                     f,
                     "T {:8.3} {:8.3} ({:8.3}, {:8.3}, {:8.3})",
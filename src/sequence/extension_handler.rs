@@ -0,0 +1,93 @@
+// User defined interpretation of extensions that pulseq-rs does not know
+use std::{any::Any, collections::HashMap, error::Error as StdError, fmt::Debug};
+
+use super::*;
+
+/// Parses the objects of an extension into a typed value. Handlers are
+/// registered with `LoadOptions::extension` and are used for all extensions
+/// that are not interpreted by pulseq-rs itself. The parsed value is stored
+/// in `Extension::value`.
+pub trait ExtensionHandler: Send + Sync + 'static {
+    type Output: Any + Send + Sync;
+    type Error: StdError + Send + Sync + 'static;
+
+    /// Name of the extension, as in the `extension NAME ID` line
+    fn name(&self) -> &str;
+
+    /// Parses the data of an extension object, which is everything after
+    /// its ID. Errors abort loading the sequence.
+    fn parse(&self, data: &str) -> Result<Self::Output, Self::Error>;
+}
+
+/// Object safe version of `ExtensionHandler`
+trait ErasedHandler: Send + Sync {
+    fn parse(&self, data: &str) -> Result<ExtensionValue, Box<dyn StdError + Send + Sync>>;
+}
+
+impl<H: ExtensionHandler> ErasedHandler for H {
+    fn parse(&self, data: &str) -> Result<ExtensionValue, Box<dyn StdError + Send + Sync>> {
+        match ExtensionHandler::parse(self, data) {
+            Ok(value) => Ok(Arc::new(value)),
+            Err(err) => Err(Box::new(err)),
+        }
+    }
+}
+
+/// A parsed extension object, see `Extension::value`
+pub type ExtensionValue = Arc<dyn Any + Send + Sync>;
+
+/// The handlers registered for loading, by extension name
+#[derive(Default, Clone)]
+pub struct ExtensionHandlers {
+    handlers: HashMap<String, Arc<dyn ErasedHandler>>,
+}
+
+impl ExtensionHandlers {
+    /// Registers the handler, replacing a previous one with the same name
+    pub fn register<H: ExtensionHandler>(&mut self, handler: H) {
+        self.handlers
+            .insert(handler.name().to_owned(), Arc::new(handler));
+    }
+
+    /// Parses the data with the handler for this extension. Returns `None`
+    /// if no handler is registered for it.
+    pub(crate) fn parse(
+        &self,
+        name: &str,
+        data: &str,
+    ) -> Option<Result<ExtensionValue, error::ConversionError>> {
+        let handler = self.handlers.get(name)?;
+        Some(
+            handler
+                .parse(data)
+                .map_err(|source| error::ConversionError::ExtensionHandler {
+                    name: name.to_owned(),
+                    source,
+                }),
+        )
+    }
+}
+
+impl Debug for ExtensionHandlers {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let mut names: Vec<_> = self.handlers.keys().collect();
+        names.sort();
+        f.debug_set().entries(names).finish()
+    }
+}
+
+impl Extension {
+    /// The value parsed by the registered `ExtensionHandler`, if there is one
+    /// for this extension and its output is of type `T`.
+    pub fn value<T: Any>(&self) -> Option<&T> {
+        self.value.as_ref()?.downcast_ref()
+    }
+}
+
+impl Block {
+    /// Values of all extensions of this block that were parsed into `T` by
+    /// an `ExtensionHandler`, in the order of the extension list.
+    pub fn extension_values<T: Any>(&self) -> impl Iterator<Item = &T> {
+        self.extensions.iter().filter_map(|ext| ext.value())
+    }
+}
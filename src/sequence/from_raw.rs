@@ -1,12 +1,9 @@
-use std::{
-    collections::{hash_map::Entry, HashMap},
-    hash::Hash,
-};
+use std::collections::{hash_map::Entry, HashMap, HashSet};
 
 use super::*;
 use crate::{
-    error::{ConversionError, MissingDefinition, ParseFovError, SectionType},
-    parse_file::{BlockDuration, Section, Version},
+    error::{ConversionError, MissingDefinition, ParseFovError, SectionType, Warning},
+    parse_file::{BlockDuration, Extensions, Section, SourceLine, Version},
 };
 
 macro_rules! extract {
@@ -25,32 +22,130 @@ macro_rules! extract {
     }};
 }
 
-fn convert_sec<Data, Key: Eq + Hash, Val, F: FnMut(Data) -> Result<(Key, Val), ConversionError>>(
+/// Decides if recoverable problems are errors (strict) or warnings (lenient)
+pub struct Diagnostics {
+    pub lenient: bool,
+    pub warnings: Vec<Warning>,
+}
+
+impl Diagnostics {
+    pub fn new(lenient: bool) -> Self {
+        Self {
+            lenient,
+            warnings: Vec::new(),
+        }
+    }
+
+    /// Records the warning in lenient mode, only used for things that are
+    /// not an error in strict mode
+    fn warn(&mut self, warning: Warning) {
+        if self.lenient {
+            self.warnings.push(warning);
+        }
+    }
+
+    /// Returns the error in strict mode, records the warning otherwise
+    fn recover(&mut self, error: ConversionError, warning: Warning) -> Result<(), ConversionError> {
+        if self.lenient {
+            self.warnings.push(warning);
+            Ok(())
+        } else {
+            Err(error)
+        }
+    }
+
+    /// Like `recover`, for warnings that contain the error
+    fn recover_with(
+        &mut self,
+        error: ConversionError,
+        warning: impl FnOnce(ConversionError) -> Warning,
+    ) -> Result<(), ConversionError> {
+        if self.lenient {
+            self.warnings.push(warning(error));
+            Ok(())
+        } else {
+            Err(error)
+        }
+    }
+}
+
+/// Converts all entries of a section. IDs are checked first, so that
+/// duplicates dropped in lenient mode are never converted.
+fn convert_sec<Data: SourceLine, Val, F: FnMut(Data) -> Result<Val, ConversionError>>(
     ty: SectionType,
     sec_data: Vec<Vec<Data>>,
-    f: F,
-) -> Result<HashMap<Key, Val>, ConversionError> {
-    let tmp = sec_data
-        .into_iter()
-        .flatten()
-        .map(f)
-        .collect::<Result<Vec<_>, ConversionError>>()?;
-    let count = tmp.len();
-    let tmp: HashMap<_, _> = tmp.into_iter().collect();
-
-    if tmp.len() < count {
-        Err(ConversionError::EventIdReuse(ty))
-    } else {
-        Ok(tmp)
+    diag: &mut Diagnostics,
+    mut f: F,
+) -> Result<HashMap<u32, Val>, ConversionError> {
+    let mut converted = HashMap::new();
+    for data in sec_data.into_iter().flatten() {
+        let line = data.line();
+        let id = data.id();
+        match converted.entry(id) {
+            Entry::Vacant(entry) => {
+                entry.insert(f(data).map_err(|err| err.at_line(line))?);
+            }
+            Entry::Occupied(_) => diag.recover(
+                ConversionError::EventIdReuse(ty).at_line(line),
+                Warning::DuplicateId {
+                    section: ty,
+                    id,
+                    line: Some(line).filter(|&line| line > 0),
+                },
+            )?,
+        }
     }
+    Ok(converted)
 }
 
-pub fn from_raw(mut sections: Vec<Section>) -> Result<Sequence, ConversionError> {
+pub fn from_raw(sections: Vec<Section>) -> Result<Sequence, ConversionError> {
+    from_raw_with(
+        sections,
+        &mut Diagnostics::new(false),
+        &ExtensionHandlers::default(),
+    )
+}
+
+/// Like `from_raw`, but in lenient mode recovers from duplicate IDs and
+/// missing or broken definitions, collecting warnings in `diag`.
+/// Extensions without built-in support are parsed by `handlers`.
+pub fn from_raw_with(
+    sections: Vec<Section>,
+    diag: &mut Diagnostics,
+    handlers: &ExtensionHandlers,
+) -> Result<Sequence, ConversionError> {
+    let (unknown, mut sections): (Vec<_>, Vec<_>) = sections
+        .into_iter()
+        .partition(|section| matches!(section, Section::Unknown { .. }));
+    let unknown_sections = unknown
+        .into_iter()
+        .filter_map(|section| match section {
+            Section::Unknown { name, lines, line } => {
+                diag.warn(Warning::UnknownSection {
+                    name: name.clone(),
+                    line,
+                });
+                Some(UnknownSection { name, lines })
+            }
+            _ => None,
+        })
+        .collect();
+
     // Destructure into single section or return error
     let [version]: [Version; 1] = extract!(sections, Version)
         .try_into()
         .map_err(|v: Vec<Version>| ConversionError::VersionSectionCount(v.len()))?;
 
+    let signature = match extract!(sections, Signature).as_slice() {
+        [] => None,
+        [sig] => Some(Signature {
+            typ: sig.typ.clone(),
+            hash: sig.hash.clone(),
+            computed: None,
+        }),
+        sigs => return Err(ConversionError::SignatureSectionCount(sigs.len())),
+    };
+
     let Defs {
         name,
         fov,
@@ -62,85 +157,141 @@ pub fn from_raw(mut sections: Vec<Section>) -> Result<Sequence, ConversionError>
             .into_iter()
             .flatten()
             .collect(),
+        diag,
     )?;
 
     let mut shape_lib = ShapeLib::new(convert_sec(
         SectionType::Shapes,
         extract!(sections, Shapes),
-        |shape| Ok((shape.id, Arc::new(Shape(shape.samples)))),
+        diag,
+        |shape| {
+            Ok(Arc::new(Shape {
+                samples: shape.samples,
+                id: Some(shape.id),
+                time_id: None,
+            }))
+        },
     )?)?;
-    let delays = convert_sec(SectionType::Delays, extract!(sections, Delays), |delay| {
-        Ok((delay.id, delay.delay))
-    })?;
-    let adcs = convert_sec(SectionType::Adcs, extract!(sections, Adcs), |adc| {
-        Ok((
-            adc.id,
-            Arc::new(Adc {
-                num: adc.num,
-                dwell: adc.dwell,
-                delay: adc.delay,
-                freq: adc.freq,
-                phase: adc.phase,
-            }),
-        ))
+    let delays = convert_sec(
+        SectionType::Delays,
+        extract!(sections, Delays),
+        diag,
+        |delay| Ok(delay.delay),
+    )?;
+    let adcs = convert_sec(SectionType::Adcs, extract!(sections, Adcs), diag, |adc| {
+        Ok(Arc::new(Adc {
+            id: Some(adc.id),
+            num: adc.num,
+            dwell: adc.dwell,
+            delay: adc.delay,
+            freq: adc.freq,
+            phase: adc.phase,
+            freq_ppm: adc.freq_ppm,
+            phase_ppm: adc.phase_ppm,
+            phase_shape: (adc.phase_id != 0)
+                .then(|| shape_lib.get(adc.phase_id, 0))
+                .transpose()?,
+        }))
     })?;
-    let rfs = convert_sec(SectionType::Rfs, extract!(sections, Rfs), |rf| {
-        Ok((
-            rf.id,
-            Arc::new(Rf {
-                amp: rf.amp,
-                phase: rf.phase,
-                amp_shape: shape_lib.get(rf.mag_id, rf.time_id)?,
-                phase_shape: shape_lib.get(rf.phase_id, rf.time_id)?,
-                delay: rf.delay,
-                freq: rf.freq,
-                shim_shape: match rf.shim_id {
-                    Some((mag_id, phase_id)) => {
-                        Some((shape_lib.get(mag_id, 0)?, shape_lib.get(phase_id, 0)?))
-                    }
-                    None => None,
-                },
-            }),
-        ))
+    let rfs = convert_sec(SectionType::Rfs, extract!(sections, Rfs), diag, |rf| {
+        Ok(Arc::new(Rf {
+            id: Some(rf.id),
+            amp: rf.amp,
+            phase: rf.phase,
+            amp_shape: shape_lib.get(rf.mag_id, rf.time_id)?,
+            phase_shape: shape_lib.get(rf.phase_id, rf.time_id)?,
+            delay: rf.delay,
+            freq: rf.freq,
+            freq_ppm: rf.freq_ppm,
+            phase_ppm: rf.phase_ppm,
+            center: rf.center,
+            usage: convert_rf_use(rf.rf_use)?,
+            shim_shape: match rf.shim_id {
+                Some((mag_id, phase_id)) => {
+                    Some((shape_lib.get(mag_id, 0)?, shape_lib.get(phase_id, 0)?))
+                }
+                None => None,
+            },
+        }))
     })?;
     let mut gradients = convert_sec(
         SectionType::Gradients,
         extract!(sections, Gradients),
+        diag,
         |grad| {
-            Ok((
-                grad.id,
-                Arc::new(Gradient::Free {
-                    amp: grad.amp,
-                    shape: shape_lib.get(grad.shape_id, grad.time_id)?,
-                    delay: grad.delay,
-                }),
-            ))
+            // Time shaped gradients start and end exactly on their first and
+            // last sample, which is lost when expanding them. Others only
+            // store first and last since pulseq 1.5, before they are 0 like
+            // in pypulseq.
+            let (first, last) = match (grad.first, grad.last) {
+                (Some(first), Some(last)) => (first, last),
+                _ => shape_lib
+                    .edges(grad.shape_id, grad.time_id)?
+                    .map_or((0.0, 0.0), |(first, last)| {
+                        (grad.amp * first, grad.amp * last)
+                    }),
+            };
+            Ok(Arc::new(Gradient::Free {
+                id: Some(grad.id),
+                amp: grad.amp,
+                first: Some(first),
+                last: Some(last),
+                shape: shape_lib.get(grad.shape_id, grad.time_id)?,
+                delay: grad.delay,
+            }))
         },
     )?;
-    let traps = convert_sec(SectionType::Traps, extract!(sections, Traps), |trap| {
-        Ok((
-            trap.id,
-            Arc::new(Gradient::Trap {
+    let traps = convert_sec(
+        SectionType::Traps,
+        extract!(sections, Traps),
+        diag,
+        |trap| {
+            Ok(Arc::new(Gradient::Trap {
+                id: Some(trap.id),
                 amp: trap.amp,
                 rise: trap.rise,
                 flat: trap.flat,
                 fall: trap.fall,
                 delay: trap.delay,
-            }),
-        ))
-    })?;
+            }))
+        },
+    )?;
 
     // Gradients and Traps share keys
-    let count = gradients.len() + traps.len();
-    gradients.extend(traps);
-    if gradients.len() < count {
-        return Err(ConversionError::GradTrapIdReuse);
+    for (id, trap) in traps {
+        match gradients.entry(id) {
+            Entry::Vacant(entry) => {
+                entry.insert(trap);
+            }
+            Entry::Occupied(_) => diag.recover(
+                ConversionError::GradTrapIdReuse,
+                Warning::DuplicateId {
+                    section: SectionType::Traps,
+                    id,
+                    line: None,
+                },
+            )?,
+        }
     }
 
+    let ext_lib = ExtensionLib::new(extract!(sections, Extensions), diag, handlers)?;
+
     let blocks = extract!(sections, Blocks)
         .into_iter()
         .flatten()
-        .map(|block| convert_block(block, &rfs, &gradients, &adcs, &delays, &time_raster))
+        .map(|block| {
+            let line = block.line;
+            convert_block(
+                block,
+                &rfs,
+                &gradients,
+                &adcs,
+                &delays,
+                &ext_lib,
+                &time_raster,
+            )
+            .map_err(|err| err.at_line(line))
+        })
         .collect::<Result<Vec<Block>, ConversionError>>()?;
 
     Ok(Sequence {
@@ -149,6 +300,8 @@ pub fn from_raw(mut sections: Vec<Section>) -> Result<Sequence, ConversionError>
         definitions: defs,
         time_raster,
         blocks,
+        signature,
+        unknown_sections,
     })
 }
 
@@ -161,13 +314,25 @@ struct Defs {
     defs: HashMap<String, String>,
 }
 
-fn convert_defs(version: &Version, defs: Vec<(String, String)>) -> Result<Defs, ConversionError> {
-    let def_count = defs.len();
-    let mut defs: HashMap<_, _> = defs.into_iter().collect();
-    if defs.len() < def_count {
-        // Duplicated key
-        return Err(ConversionError::NonUniqueDefinition);
+fn convert_defs(
+    version: &Version,
+    defs: Vec<(String, String)>,
+    diag: &mut Diagnostics,
+) -> Result<Defs, ConversionError> {
+    let mut map = HashMap::new();
+    for (key, value) in defs {
+        match map.entry(key) {
+            Entry::Vacant(entry) => {
+                entry.insert(value);
+            }
+            // Like duplicated IDs, the first value is used
+            Entry::Occupied(entry) => diag.recover(
+                ConversionError::NonUniqueDefinition,
+                Warning::DuplicateDefinition(entry.key().clone()),
+            )?,
+        }
     }
+    let mut defs = map;
 
     // Before 1.4, there is no spec on what's inside of a definition, so we
     // just directly return. Raster times are not exported by older exporters,
@@ -176,7 +341,7 @@ fn convert_defs(version: &Version, defs: Vec<(String, String)>) -> Result<Defs,
         version,
         Version {
             major: 1,
-            minor: 4,
+            minor: 4 | 5,
             ..
         }
     ) {
@@ -188,26 +353,54 @@ fn convert_defs(version: &Version, defs: Vec<(String, String)>) -> Result<Defs,
         });
     }
 
+    let default = TimeRaster::default();
     let time_raster = TimeRaster {
-        grad: defs
-            .remove("GradientRasterTime")
-            .ok_or(MissingDefinition::GradientRasterTime)?
-            .parse()?,
-        rf: defs
-            .remove("RadiofrequencyRasterTime")
-            .ok_or(MissingDefinition::RadiofrequencyRasterTime)?
-            .parse()?,
-        adc: defs
-            .remove("AdcRasterTime")
-            .ok_or(MissingDefinition::AdcRasterTime)?
-            .parse()?,
-        block: defs
-            .remove("BlockDurationRaster")
-            .ok_or(MissingDefinition::BlockDurationRaster)?
-            .parse()?,
+        grad: raster_def(
+            &mut defs,
+            "GradientRasterTime",
+            MissingDefinition::GradientRasterTime,
+            default.grad,
+            diag,
+        )?,
+        rf: raster_def(
+            &mut defs,
+            "RadiofrequencyRasterTime",
+            MissingDefinition::RadiofrequencyRasterTime,
+            default.rf,
+            diag,
+        )?,
+        adc: raster_def(
+            &mut defs,
+            "AdcRasterTime",
+            MissingDefinition::AdcRasterTime,
+            default.adc,
+            diag,
+        )?,
+        block: raster_def(
+            &mut defs,
+            "BlockDurationRaster",
+            MissingDefinition::BlockDurationRaster,
+            default.block,
+            diag,
+        )?,
     };
     let name = defs.remove("Name");
-    let fov = defs.remove("FOV").map(parse_fov).transpose()?;
+    let fov = match defs.remove("FOV") {
+        Some(value) => match parse_fov(&value) {
+            Ok(fov) => Some(fov),
+            Err(err) => {
+                diag.recover(
+                    err.into(),
+                    Warning::InvalidDefinition {
+                        key: "FOV".to_owned(),
+                        value,
+                    },
+                )?;
+                None
+            }
+        },
+        None => None,
+    };
 
     Ok(Defs {
         name,
@@ -217,12 +410,36 @@ fn convert_defs(version: &Version, defs: Vec<(String, String)>) -> Result<Defs,
     })
 }
 
+/// Removes and parses a mandatory raster time definition. In lenient mode,
+/// missing or broken definitions are replaced by the default.
+fn raster_def(
+    defs: &mut HashMap<String, String>,
+    key: &str,
+    missing: MissingDefinition,
+    default: f64,
+    diag: &mut Diagnostics,
+) -> Result<f64, ConversionError> {
+    let Some(value) = defs.remove(key) else {
+        diag.recover(missing.into(), Warning::MissingDefinition(missing))?;
+        return Ok(default);
+    };
+    match value.parse() {
+        Ok(raster) => Ok(raster),
+        Err(err) => {
+            let key = key.to_owned();
+            diag.recover(err.into(), Warning::InvalidDefinition { key, value })?;
+            Ok(default)
+        }
+    }
+}
+
 fn convert_block(
     block: crate::parse_file::Block,
     rfs: &HashMap<u32, Arc<Rf>>,
     gradients: &HashMap<u32, Arc<Gradient>>,
     adcs: &HashMap<u32, Arc<Adc>>,
     delays: &HashMap<u32, f64>,
+    ext_lib: &ExtensionLib,
     time_raster: &TimeRaster,
 ) -> Result<Block, ConversionError> {
     let err = |ty, id| ConversionError::BrokenRef { ty, id };
@@ -244,6 +461,36 @@ fn convert_block(
         .then(|| adcs.get(&block.adc).cloned().ok_or(err(Adc, block.adc)))
         .transpose()?;
 
+    // Known extensions are converted, others are passed through
+    let mut labels = Vec::new();
+    let mut triggers = Vec::new();
+    let mut rotation = None;
+    let mut soft_delay = None;
+    let mut extensions = Vec::new();
+    for ext in ext_lib.resolve(block.ext)? {
+        match ext {
+            ExtensionObject::Label(label) => labels.push(label),
+            ExtensionObject::Trigger(trigger) => triggers.push(trigger),
+            ExtensionObject::Rotation(rot) => {
+                if rotation.replace(rot).is_some() {
+                    return Err(ConversionError::DuplicateExtension {
+                        name: "ROTATIONS",
+                        block_id: block.id,
+                    });
+                }
+            }
+            ExtensionObject::SoftDelay(delay) => {
+                if soft_delay.replace(delay).is_some() {
+                    return Err(ConversionError::DuplicateExtension {
+                        name: "DELAYS",
+                        block_id: block.id,
+                    });
+                }
+            }
+            ExtensionObject::Other(ext) => extensions.push(ext),
+        }
+    }
+
     let duration = match block.dur {
         BlockDuration::Duration(dur) => dur as f64 * time_raster.block,
         BlockDuration::DelayId(delay) => {
@@ -260,6 +507,7 @@ fn convert_block(
                 delay,
             ]
             .into_iter()
+            .chain(triggers.iter().map(|t| Some(t.total_duration())))
             .flatten()
             .max_by(|a, b| a.total_cmp(b))
             .unwrap_or(0.0)
@@ -274,10 +522,28 @@ fn convert_block(
         gy,
         gz,
         adc,
+        labels,
+        triggers,
+        rotation,
+        soft_delay,
+        extensions,
     })
 }
 
-fn parse_fov(s: String) -> Result<(f64, f64, f64), ParseFovError> {
+fn convert_rf_use(rf_use: char) -> Result<RfUse, ConversionError> {
+    Ok(match rf_use {
+        'e' => RfUse::Excitation,
+        'r' => RfUse::Refocusing,
+        'i' => RfUse::Inversion,
+        's' => RfUse::Saturation,
+        'p' => RfUse::Preparation,
+        'o' => RfUse::Other,
+        'u' => RfUse::Undefined,
+        _ => return Err(ConversionError::UnknownRfUse(rf_use)),
+    })
+}
+
+fn parse_fov(s: &str) -> Result<(f64, f64, f64), ParseFovError> {
     let splits: Vec<_> = s.split_whitespace().collect();
     if splits.len() != 3 {
         Err(ParseFovError::WrongValueCount(splits.len()))
@@ -286,6 +552,139 @@ fn parse_fov(s: String) -> Result<(f64, f64, f64), ParseFovError> {
     }
 }
 
+/// Extension objects are converted once when loading the [EXTENSIONS] section,
+/// so that blocks referencing the same object share it.
+#[derive(Clone)]
+enum ExtensionObject {
+    Label(Label),
+    Trigger(Arc<Trigger>),
+    Rotation(Arc<Rotation>),
+    SoftDelay(Arc<SoftDelay>),
+    Other(Arc<Extension>),
+}
+
+impl ExtensionObject {
+    fn new(
+        name: &str,
+        data: &str,
+        handlers: &ExtensionHandlers,
+    ) -> Result<Self, error::ConversionError> {
+        Ok(match name {
+            "LABELSET" => Self::Label(Label::parse(LabelOp::Set, data)?),
+            "LABELINC" => Self::Label(Label::parse(LabelOp::Inc, data)?),
+            "TRIGGERS" => Self::Trigger(Arc::new(Trigger::parse(data)?)),
+            "ROTATIONS" => Self::Rotation(Arc::new(Rotation::parse(data)?)),
+            "DELAYS" => Self::SoftDelay(Arc::new(SoftDelay::parse(data)?)),
+            _ => Self::Other(Arc::new(Extension {
+                name: name.to_owned(),
+                value: handlers.parse(name, data).transpose()?,
+                data: data.to_owned(),
+            })),
+        })
+    }
+
+    /// Extensions that are passed through as `Extension` in lenient mode if
+    /// they can't be interpreted, e.g. labels added by newer pulseq versions
+    fn has_fallback(name: &str) -> bool {
+        matches!(name, "LABELSET" | "LABELINC" | "TRIGGERS")
+    }
+}
+
+struct ExtensionLib {
+    /// ID -> (spec_id, obj_id, next)
+    refs: HashMap<u32, (u32, u32, u32)>,
+    objects: HashMap<(u32, u32), ExtensionObject>,
+}
+
+impl ExtensionLib {
+    fn new(
+        sections: Vec<Extensions>,
+        diag: &mut Diagnostics,
+        handlers: &ExtensionHandlers,
+    ) -> Result<Self, error::ConversionError> {
+        let mut ref_data = Vec::new();
+        let mut spec_data = Vec::new();
+        for ext in sections {
+            ref_data.push(ext.refs);
+            spec_data.push(ext.specs);
+        }
+
+        let refs = convert_sec(SectionType::Extensions, ref_data, diag, |r| {
+            Ok((r.spec_id, r.obj_id, r.next))
+        })?;
+        let specs = convert_sec(SectionType::Extensions, spec_data, diag, Ok)?;
+
+        let mut objects = HashMap::new();
+        for (spec_id, spec) in specs {
+            for obj in spec.instances {
+                let line = obj.line;
+                match objects.entry((spec_id, obj.id)) {
+                    Entry::Vacant(entry) => {
+                        let ext = match ExtensionObject::new(&spec.name, &obj.data, handlers) {
+                            Ok(ext) => ext,
+                            Err(error) if ExtensionObject::has_fallback(&spec.name) => {
+                                diag.recover_with(error.at_line(line), |error| {
+                                    Warning::UninterpretedExtension {
+                                        name: spec.name.clone(),
+                                        error: error.without_line(),
+                                        line: Some(line).filter(|&line| line > 0),
+                                    }
+                                })?;
+                                ExtensionObject::Other(Arc::new(Extension {
+                                    name: spec.name.clone(),
+                                    data: obj.data,
+                                    value: None,
+                                }))
+                            }
+                            Err(error) => return Err(error.at_line(line)),
+                        };
+                        entry.insert(ext);
+                    }
+                    Entry::Occupied(_) => diag.recover(
+                        ConversionError::EventIdReuse(SectionType::Extensions).at_line(line),
+                        Warning::DuplicateId {
+                            section: SectionType::Extensions,
+                            id: obj.id,
+                            line: Some(line).filter(|&line| line > 0),
+                        },
+                    )?,
+                }
+            }
+        }
+
+        Ok(Self { refs, objects })
+    }
+
+    /// Follows the linked list of extensions starting at `id` (0 = no extension)
+    fn resolve(&self, mut id: u32) -> Result<Vec<ExtensionObject>, error::ConversionError> {
+        let start = id;
+        let mut visited = HashSet::new();
+        let mut extensions = Vec::new();
+
+        while id != 0 {
+            if !visited.insert(id) {
+                return Err(ConversionError::ExtensionCycle(start));
+            }
+            let &(spec_id, obj_id, next) =
+                self.refs.get(&id).ok_or(ConversionError::BrokenRef {
+                    ty: EventType::Extension,
+                    id,
+                })?;
+            let ext = self.objects.get(&(spec_id, obj_id)).ok_or(
+                ConversionError::BrokenExtensionRef {
+                    ref_id: id,
+                    spec_id,
+                    obj_id,
+                },
+            )?;
+            extensions.push(ext.clone());
+            id = next;
+        }
+
+        Ok(extensions)
+    }
+}
+
 struct ShapeLib {
     shapes: HashMap<u32, Arc<Shape>>,
     memo: HashMap<(u32, u32), Arc<Shape>>,
@@ -323,12 +722,36 @@ impl ShapeLib {
             match self.memo.entry((shape_id, time_id)) {
                 Entry::Occupied(e) => Ok(e.get().clone()),
                 Entry::Vacant(e) => {
-                    let expanded = Arc::new(expand_shape(shape, time)?);
+                    let expanded = Arc::new(Shape {
+                        samples: expand_shape(shape, time, shape_id, time_id)?,
+                        id: Some(shape_id),
+                        time_id: Some(time_id),
+                    });
                     Ok(e.insert(expanded).clone())
                 }
             }
         }
     }
+
+    /// Returns the first and last sample of time shaped shapes
+    fn edges(
+        &self,
+        shape_id: u32,
+        time_id: u32,
+    ) -> Result<Option<(f64, f64)>, error::ConversionError> {
+        if time_id == 0 {
+            return Ok(None);
+        }
+        let shape = self
+            .shapes
+            .get(&shape_id)
+            .ok_or(ConversionError::ShapeNotFound(shape_id))?;
+        Ok(shape
+            .samples
+            .first()
+            .zip(shape.samples.last())
+            .map(|(a, b)| (*a, *b)))
+    }
 }
 
 /// Here we do interpolation as given by the time shape. The spec unfortunately does not
@@ -346,33 +769,40 @@ impl ShapeLib {
 /// implementing time shape expansion right now.
 /// In addition, we use linar interpolation (as it seems to be expected when using
 /// this feature for trap grads). The spec does not say anything about interpolation at all.
-fn expand_shape(shape: &Arc<Shape>, time: &Arc<Shape>) -> Result<Shape, ConversionError> {
-    if shape.0.len() != time.0.len() {
+fn expand_shape(
+    shape: &Shape,
+    time: &Shape,
+    shape_id: u32,
+    time_id: u32,
+) -> Result<Vec<f64>, ConversionError> {
+    if shape.samples.len() != time.samples.len() {
         return Err(ConversionError::TimeShapeMismatch {
-            shape_len: shape.0.len(),
-            time_len: time.0.len(),
+            shape_id,
+            time_id,
+            shape_len: shape.samples.len(),
+            time_len: time.samples.len(),
         });
     }
 
     // Probably a bug but technically not an error
-    if shape.0.is_empty() {
-        return Ok(Shape(Vec::new()));
+    if shape.samples.is_empty() {
+        return Ok(Vec::new());
     }
 
     // Check if numbers in this shape are all integer, then convert to integers
-    if time.0.iter().any(|x| x.fract() != 0.0) {
-        return Err(ConversionError::TimeShapeNonInteger);
+    if time.samples.iter().any(|x| x.fract() != 0.0) {
+        return Err(ConversionError::TimeShapeNonInteger(time_id));
     }
-    let time: Vec<_> = time.0.iter().map(|x| *x as u32).collect();
+    let time: Vec<_> = time.samples.iter().map(|x| *x as u32).collect();
 
     // Do the actual conversion
     let mut expanded = Vec::with_capacity(*time.last().unwrap_or(&0) as usize);
-    let mut amp = shape.0[0];
+    let mut amp = shape.samples[0];
 
-    for (len, &next_amp) in time.into_iter().zip(shape.0.iter()) {
+    for (len, &next_amp) in time.into_iter().zip(shape.samples.iter()) {
         // If we are suddenly too long, time shape is not striclty increasing
         if expanded.len() > len as usize {
-            return Err(ConversionError::TimeShapeNonIncreasing);
+            return Err(ConversionError::TimeShapeNonIncreasing(time_id));
         }
         // Interpolate between amp and next_amp in line_len steps
         let line_len = len - expanded.len() as u32;
@@ -383,5 +813,5 @@ fn expand_shape(shape: &Arc<Shape>, time: &Arc<Shape>) -> Result<Shape, Conversi
         amp = next_amp;
     }
 
-    Ok(Shape(expanded))
+    Ok(expanded)
 }
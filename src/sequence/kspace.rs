@@ -0,0 +1,193 @@
+// k-space trajectory calculation, similar to pypulseq's calculate_kspace
+
+use super::*;
+
+/// The k-space trajectory of a sequence. All k-space positions are given in
+/// `[1/m]` as `[kx, ky, kz]`, all times in `[s]`.
+pub struct KSpace {
+    /// Times of all gradient corners, RF centers and ADC samples
+    pub t: Vec<f64>,
+    /// k-space position at the times in `t`. For RF pulses, this is the
+    /// position right after the pulse was applied.
+    pub k: Vec<[f64; 3]>,
+    /// Time of every ADC sample: the center of its dwell interval
+    pub t_adc: Vec<f64>,
+    /// k-space position of every ADC sample
+    pub k_adc: Vec<[f64; 3]>,
+    /// Centers of all excitation pulses, which reset k to zero
+    pub t_excitation: Vec<f64>,
+    /// Centers of all refocusing pulses, which invert k
+    pub t_refocusing: Vec<f64>,
+}
+
+impl Sequence {
+    /// Calculates the k-space trajectory by integrating the gradients.
+    /// Gradients are piecewise linear (see `Gradient::breakpoints`), so the
+    /// integration is exact. Pulses are treated as instantaneous at their
+    /// center; pulses with an undefined use are guessed by their flip angle,
+    /// like pypulseq does: below 90.01° excitation, refocusing otherwise.
+    /// Rotations (ROTATIONS extension) are not applied.
+    pub fn kspace(&self) -> KSpace {
+        let timeline = self.timeline();
+        let raster = &self.time_raster;
+
+        let mut channels = [
+            Integral::default(),
+            Integral::default(),
+            Integral::default(),
+        ];
+        let mut t_excitation = Vec::new();
+        let mut t_refocusing = Vec::new();
+        let mut t_adc = Vec::new();
+
+        for (index, block) in self.blocks.iter().enumerate() {
+            let start = timeline.block_start(index);
+
+            for (channel, grad) in channels.iter_mut().zip([&block.gx, &block.gy, &block.gz]) {
+                if let Some(grad) = grad {
+                    channel.add(start, &grad.breakpoints(raster.grad));
+                }
+            }
+            if let Some(rf) = &block.rf {
+                let t = start + rf.delay + rf.center_or_peak(raster.rf);
+                match rf.kspace_use(raster.rf) {
+                    RfUse::Excitation => t_excitation.push(t),
+                    RfUse::Refocusing => t_refocusing.push(t),
+                    _ => (),
+                }
+            }
+            if let Some(adc) = &block.adc {
+                t_adc
+                    .extend((0..adc.num).map(|i| start + adc.delay + (i as f64 + 0.5) * adc.dwell));
+            }
+        }
+
+        // All points where the trajectory changes its direction or jumps
+        let mut t: Vec<f64> = channels
+            .iter()
+            .flat_map(|channel| channel.segments.iter().flat_map(|s| [s.t0, s.t1]))
+            .chain(t_excitation.iter().copied())
+            .chain(t_refocusing.iter().copied())
+            .chain(t_adc.iter().copied())
+            .collect();
+        t.sort_by(f64::total_cmp);
+        t.dedup();
+
+        // Process all points in order, applying RF pulses as they occur
+        let mut rf_events: Vec<_> = (t_excitation.iter().map(|&t| (t, RfUse::Excitation)))
+            .chain(t_refocusing.iter().map(|&t| (t, RfUse::Refocusing)))
+            .collect();
+        rf_events.sort_by(|a, b| a.0.total_cmp(&b.0));
+        let mut rf_events = rf_events.into_iter().peekable();
+
+        // k(t) = k_raw(t) - reference, where k_raw is the plain integral
+        let mut reference = [0.0; 3];
+        let eval = |t: f64, reference: &[f64; 3]| -> [f64; 3] {
+            std::array::from_fn(|i| channels[i].at(t) - reference[i])
+        };
+
+        let mut k = Vec::with_capacity(t.len());
+        for &t in &t {
+            while let Some((t_rf, rf_use)) = rf_events.next_if(|&(t_rf, _)| t_rf <= t) {
+                let k_raw = eval(t_rf, &[0.0; 3]);
+                reference = std::array::from_fn(|i| match rf_use {
+                    RfUse::Excitation => k_raw[i],
+                    _ => 2.0 * k_raw[i] - reference[i],
+                });
+            }
+            k.push(eval(t, &reference));
+        }
+
+        // ADC samples are part of t, look them up instead of re-evaluating
+        let k_adc = t_adc
+            .iter()
+            .map(|t_adc| k[t.partition_point(|&t| t < *t_adc)])
+            .collect();
+
+        KSpace {
+            t,
+            k,
+            t_adc,
+            k_adc,
+            t_excitation,
+            t_refocusing,
+        }
+    }
+}
+
+impl Rf {
+    /// Time of the pulse center relative to the start of the shape. If not
+    /// stored in the file, this is the middle of the peak magnitude samples.
+    pub(crate) fn center_or_peak(&self, rf_raster: f64) -> f64 {
+        if let Some(center) = self.center {
+            return center;
+        }
+        self.peak_time(rf_raster)
+    }
+
+    /// The use of the pulse for k-space calculation, guessed from the flip
+    /// angle if it is not defined.
+    fn kspace_use(&self, rf_raster: f64) -> RfUse {
+        if self.usage != RfUse::Undefined {
+            return self.usage;
+        }
+        let flip_deg = self.flip_angle(rf_raster).to_degrees();
+        if flip_deg < 90.01 {
+            RfUse::Excitation
+        } else {
+            RfUse::Refocusing
+        }
+    }
+}
+
+/// Integral of a piecewise linear function, consisting of non-overlapping
+/// segments that are added in chronological order.
+#[derive(Default)]
+struct Integral {
+    segments: Vec<Segment>,
+}
+
+struct Segment {
+    t0: f64,
+    t1: f64,
+    g0: f64,
+    g1: f64,
+    /// Integral up to t0
+    area: f64,
+}
+
+impl Integral {
+    fn add(&mut self, start: f64, points: &[(f64, f64)]) {
+        for pair in points.windows(2) {
+            let [(t0, g0), (t1, g1)] = [pair[0], pair[1]];
+            let area = self.total();
+            self.segments.push(Segment {
+                t0: start + t0,
+                t1: start + t1,
+                g0,
+                g1,
+                area,
+            });
+        }
+    }
+
+    fn total(&self) -> f64 {
+        self.segments
+            .last()
+            .map_or(0.0, |s| s.area + 0.5 * (s.g0 + s.g1) * (s.t1 - s.t0))
+    }
+
+    fn at(&self, t: f64) -> f64 {
+        let index = self.segments.partition_point(|s| s.t0 <= t);
+        let Some(s) = index.checked_sub(1).map(|i| &self.segments[i]) else {
+            return 0.0;
+        };
+        let dt = t.min(s.t1) - s.t0;
+        if s.t1 > s.t0 {
+            let g = s.g0 + (s.g1 - s.g0) * dt / (s.t1 - s.t0);
+            s.area + 0.5 * (s.g0 + g) * dt
+        } else {
+            s.area
+        }
+    }
+}
@@ -0,0 +1,198 @@
+// Typed representation of the LABELSET and LABELINC extensions
+use std::{fmt::Display, str::FromStr};
+
+use super::*;
+use crate::error::ConversionError;
+
+/// A single label operation, as given by a LABELSET or LABELINC extension.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Label {
+    pub op: LabelOp,
+    pub name: LabelName,
+    pub value: i32,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum LabelOp {
+    Set,
+    Inc,
+}
+
+/// All labels supported by pypulseq. Counters are incremented or set, flags
+/// can only be set (to 0 or 1).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum LabelName {
+    // Counters
+    Slc,
+    Seg,
+    Rep,
+    Avg,
+    Set,
+    Eco,
+    Phs,
+    Lin,
+    Par,
+    Acq,
+    Trid,
+    // Flags
+    Nav,
+    Rev,
+    Sms,
+    Ref,
+    Ima,
+    Noise,
+    Pmc,
+    Norot,
+    Nopos,
+    Noscl,
+    Once,
+}
+
+impl LabelName {
+    pub const ALL: [LabelName; 22] = [
+        LabelName::Slc,
+        LabelName::Seg,
+        LabelName::Rep,
+        LabelName::Avg,
+        LabelName::Set,
+        LabelName::Eco,
+        LabelName::Phs,
+        LabelName::Lin,
+        LabelName::Par,
+        LabelName::Acq,
+        LabelName::Trid,
+        LabelName::Nav,
+        LabelName::Rev,
+        LabelName::Sms,
+        LabelName::Ref,
+        LabelName::Ima,
+        LabelName::Noise,
+        LabelName::Pmc,
+        LabelName::Norot,
+        LabelName::Nopos,
+        LabelName::Noscl,
+        LabelName::Once,
+    ];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            LabelName::Slc => "SLC",
+            LabelName::Seg => "SEG",
+            LabelName::Rep => "REP",
+            LabelName::Avg => "AVG",
+            LabelName::Set => "SET",
+            LabelName::Eco => "ECO",
+            LabelName::Phs => "PHS",
+            LabelName::Lin => "LIN",
+            LabelName::Par => "PAR",
+            LabelName::Acq => "ACQ",
+            LabelName::Trid => "TRID",
+            LabelName::Nav => "NAV",
+            LabelName::Rev => "REV",
+            LabelName::Sms => "SMS",
+            LabelName::Ref => "REF",
+            LabelName::Ima => "IMA",
+            LabelName::Noise => "NOISE",
+            LabelName::Pmc => "PMC",
+            LabelName::Norot => "NOROT",
+            LabelName::Nopos => "NOPOS",
+            LabelName::Noscl => "NOSCL",
+            LabelName::Once => "ONCE",
+        }
+    }
+
+    pub fn is_flag(&self) -> bool {
+        // ONCE is a flag in pypulseq, but can be set to 0, 1 or 2
+        (*self as usize) >= LabelName::Nav as usize
+    }
+}
+
+impl FromStr for LabelName {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        LabelName::ALL
+            .into_iter()
+            .find(|name| name.as_str() == s)
+            .ok_or(())
+    }
+}
+
+impl Display for LabelName {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        self.as_str().fmt(f)
+    }
+}
+
+impl Label {
+    /// Parses the data of a LABELSET or LABELINC extension object: `value name`
+    pub(crate) fn parse(op: LabelOp, data: &str) -> Result<Self, ConversionError> {
+        let err = || ConversionError::InvalidExtensionData {
+            name: match op {
+                LabelOp::Set => "LABELSET",
+                LabelOp::Inc => "LABELINC",
+            }
+            .to_owned(),
+            data: data.to_owned(),
+        };
+
+        let splits: Vec<_> = data.split_whitespace().collect();
+        let [value, name] = splits[..] else {
+            return Err(err());
+        };
+        let value: i32 = value.parse().map_err(|_| err())?;
+        let name: LabelName =
+            (name.parse()).map_err(|_| ConversionError::UnknownLabel(name.to_owned()))?;
+
+        if op == LabelOp::Inc && name.is_flag() {
+            return Err(ConversionError::IncrementedFlag(name));
+        }
+        Ok(Self { op, name, value })
+    }
+}
+
+/// The values of all counters and flags at some point in the sequence.
+/// All labels start at 0, which means that flags are initially not set.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct LabelState([i32; LabelName::ALL.len()]);
+
+impl LabelState {
+    pub fn get(&self, name: LabelName) -> i32 {
+        self.0[name as usize]
+    }
+
+    pub fn flag(&self, name: LabelName) -> bool {
+        self.0[name as usize] != 0
+    }
+
+    pub fn apply(&mut self, label: &Label) {
+        match label.op {
+            LabelOp::Set => self.0[label.name as usize] = label.value,
+            LabelOp::Inc => self.0[label.name as usize] += label.value,
+        }
+    }
+}
+
+impl Sequence {
+    /// Executes all blocks and returns the label state for every block that
+    /// contains an ADC. Labels of a block are applied before its ADC is played
+    /// out, in the order they appear in the block.
+    pub fn adc_labels(&self) -> Vec<(&Block, LabelState)> {
+        let mut state = LabelState::default();
+        let mut adc_labels = Vec::new();
+
+        for block in &self.blocks {
+            for label in &block.labels {
+                state.apply(label);
+            }
+            if block.adc.is_some() {
+                adc_labels.push((block, state));
+            }
+        }
+
+        adc_labels
+    }
+}
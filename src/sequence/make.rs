@@ -0,0 +1,477 @@
+// Event factories, modelled after pypulseq's make_* functions
+use std::f64::consts::{PI, TAU};
+
+use super::system::TOLERANCE;
+use super::*;
+use crate::error::MakeError;
+
+/// Parameters of `make_trapezoid`. Like in pypulseq, the trapezoid is
+/// defined by one of these combinations, checked in this order:
+/// - `flat_time` with `amplitude` or `flat_area`
+/// - `duration` with `amplitude` or `area`
+/// - `area` alone, which gives the shortest possible trapezoid
+///
+/// Ramps are as short as the slew rate allows unless `rise_time` is given,
+/// `fall_time` defaults to the rise time.
+#[derive(Debug, Clone, Default)]
+pub struct TrapezoidSpec {
+    /// Unit: `[1/m]`
+    pub area: Option<f64>,
+    /// Area of the flat top only, unit: `[1/m]`
+    pub flat_area: Option<f64>,
+    /// Unit: `[Hz/m]`
+    pub amplitude: Option<f64>,
+    /// Unit: `[s]`
+    pub flat_time: Option<f64>,
+    /// Total duration including the ramps, unit: `[s]`
+    pub duration: Option<f64>,
+    /// Unit: `[s]`
+    pub rise_time: Option<f64>,
+    /// Unit: `[s]`
+    pub fall_time: Option<f64>,
+    /// Unit: `[s]`
+    pub delay: f64,
+}
+
+/// Creates a trapezoid gradient as described by `spec`. Ramps are rounded up
+/// to the gradient raster; the amplitude, slew rate and timing are checked
+/// against the `System` limits.
+pub fn make_trapezoid(
+    spec: &TrapezoidSpec,
+    system: &System,
+    raster: &TimeRaster,
+) -> Result<Gradient, MakeError> {
+    let dt = raster.grad;
+    let min_ramp = |amp: f64| {
+        spec.rise_time
+            .unwrap_or_else(|| ceil_raster(amp.abs() / system.max_slew, dt).max(dt))
+    };
+
+    let (amp, rise, flat, fall);
+    if let Some(flat_time) = spec.flat_time {
+        amp = match (spec.amplitude, spec.flat_area) {
+            (Some(amp), _) => amp,
+            (None, Some(flat_area)) => flat_area / flat_time,
+            (None, None) => {
+                return Err(MakeError::MissingParameter(
+                    "amplitude or flat_area together with flat_time",
+                ))
+            }
+        };
+        rise = min_ramp(amp);
+        fall = spec.fall_time.unwrap_or(rise);
+        flat = flat_time;
+    } else if let Some(duration) = spec.duration {
+        match (spec.amplitude, spec.area) {
+            (Some(a), _) => {
+                amp = a;
+                rise = min_ramp(a);
+                fall = spec.fall_time.unwrap_or(rise);
+            }
+            (None, Some(area)) => {
+                (rise, fall) = match spec.rise_time {
+                    Some(rise) => (rise, spec.fall_time.unwrap_or(rise)),
+                    None => {
+                        // Ramps at full slew: area = amp * (duration - amp / slew)
+                        let slew = system.max_slew;
+                        let disc = duration * duration - 4.0 * area.abs() / slew;
+                        if disc < 0.0 {
+                            return Err(MakeError::AreaTooLarge { area, duration });
+                        }
+                        let amp = (duration - disc.sqrt()) * slew / 2.0;
+                        let ramp = ceil_raster(amp / slew, dt).max(dt);
+                        (ramp, ramp)
+                    }
+                };
+                // Keep the area exact despite rounding the ramps
+                amp = area / (duration - (rise + fall) / 2.0);
+            }
+            (None, None) => {
+                return Err(MakeError::MissingParameter(
+                    "amplitude or area together with duration",
+                ))
+            }
+        }
+        flat = duration - rise - fall;
+        if flat < -TOLERANCE * dt {
+            return Err(MakeError::TooShort {
+                duration,
+                ramps: rise + fall,
+            });
+        }
+    } else {
+        let area = spec
+            .area
+            .ok_or(MakeError::MissingParameter("area, flat_time or duration"))?;
+        // Shortest possible: a triangle, or a trapezoid at max amplitude
+        let mut ramp = ceil_raster((area.abs() / system.max_slew).sqrt(), dt).max(dt);
+        let mut total = ramp;
+        let mut a = area / ramp;
+        if a.abs() > system.max_grad {
+            total = ceil_raster(area.abs() / system.max_grad, dt);
+            a = area / total;
+            ramp = ceil_raster(a.abs() / system.max_slew, dt).max(dt);
+        }
+        amp = a;
+        rise = ramp;
+        fall = ramp;
+        flat = total - ramp;
+    }
+
+    check_amplitude(amp, system)?;
+    for ramp in [rise, fall] {
+        if ramp > 0.0 {
+            check_slew(amp / ramp, system)?;
+        }
+    }
+
+    Ok(Gradient::trap(amp, rise, flat.max(0.0), fall, spec.delay))
+}
+
+/// Creates a free gradient from its waveform in `[Hz/m]`, sampled on the
+/// gradient raster. Like pypulseq, the first and last amplitude are
+/// extrapolated half a raster interval from the outermost samples.
+pub fn make_arbitrary_grad(
+    waveform: &[f64],
+    delay: f64,
+    system: &System,
+    raster: &TimeRaster,
+) -> Result<Gradient, MakeError> {
+    let (Some(&w0), Some(&wn)) = (waveform.first(), waveform.last()) else {
+        return Err(MakeError::InvalidWaveform("waveform is empty"));
+    };
+    for &x in waveform {
+        check_amplitude(x, system)?;
+    }
+    for pair in waveform.windows(2) {
+        check_slew((pair[1] - pair[0]) / raster.grad, system)?;
+    }
+
+    let (first, last) = if waveform.len() > 1 {
+        (
+            (3.0 * w0 - waveform[1]) / 2.0,
+            (3.0 * wn - waveform[waveform.len() - 2]) / 2.0,
+        )
+    } else {
+        (w0, wn)
+    };
+
+    Ok(with_first_last(
+        Gradient::free(waveform, delay),
+        first,
+        last,
+    ))
+}
+
+/// Creates a gradient that linearly connects the given corners. `times`
+/// (unit: `[s]`) start at zero, relative to `delay`, are strictly increasing
+/// and on the gradient raster. `amplitudes` are in `[Hz/m]`. The waveform is
+/// stored as free gradient sampled on the raster, with exact first and last
+/// amplitudes, like files with a time shape are loaded.
+pub fn make_extended_trapezoid(
+    times: &[f64],
+    amplitudes: &[f64],
+    delay: f64,
+    system: &System,
+    raster: &TimeRaster,
+) -> Result<Gradient, MakeError> {
+    let dt = raster.grad;
+    if times.len() != amplitudes.len() {
+        return Err(MakeError::InvalidWaveform(
+            "times and amplitudes differ in length",
+        ));
+    }
+    if times.len() < 2 {
+        return Err(MakeError::InvalidWaveform(
+            "at least two corners are needed",
+        ));
+    }
+    if times[0] != 0.0 {
+        return Err(MakeError::InvalidWaveform("times must start at zero"));
+    }
+    for &t in times {
+        if ((t / dt).round() - t / dt).abs() > TOLERANCE {
+            return Err(MakeError::NotOnRaster {
+                what: "Corner time",
+                value: t,
+                raster: dt,
+            });
+        }
+    }
+    if times.windows(2).any(|w| w[1] <= w[0]) {
+        return Err(MakeError::InvalidWaveform("times must be increasing"));
+    }
+    for &a in amplitudes {
+        check_amplitude(a, system)?;
+    }
+    for (t, a) in times.windows(2).zip(amplitudes.windows(2)) {
+        check_slew((a[1] - a[0]) / (t[1] - t[0]), system)?;
+    }
+
+    // Sample the piecewise linear waveform at the raster centers
+    let count = (times[times.len() - 1] / dt).round() as usize;
+    let mut segment = 0;
+    let waveform: Vec<_> = (0..count)
+        .map(|i| {
+            let t = (i as f64 + 0.5) * dt;
+            while times[segment + 1] < t {
+                segment += 1;
+            }
+            let (t0, t1) = (times[segment], times[segment + 1]);
+            let (a0, a1) = (amplitudes[segment], amplitudes[segment + 1]);
+            a0 + (a1 - a0) * (t - t0) / (t1 - t0)
+        })
+        .collect();
+
+    Ok(with_first_last(
+        Gradient::free(&waveform, delay),
+        amplitudes[0],
+        amplitudes[amplitudes.len() - 1],
+    ))
+}
+
+/// Parameters shared by `make_sinc_pulse`, `make_gauss_pulse` and
+/// `make_block_pulse`. The defaults are those of pypulseq.
+#[derive(Debug, Clone)]
+pub struct PulseSpec {
+    /// Unit: `[s]`, raised to `System::rf_dead_time` if shorter
+    pub delay: f64,
+    /// Unit: `[Hz]`
+    pub freq_offset: f64,
+    /// Unit: `[rad]`
+    pub phase_offset: f64,
+    /// Defines the bandwidth as `time_bw_product / duration`. Default: 4
+    pub time_bw_product: f64,
+    /// Weight of the Hanning window applied to sinc and gauss pulses,
+    /// from 0 (none) to 1. Default: 0
+    pub apodization: f64,
+    /// Position of the pulse center as fraction of the duration.
+    /// Default: 0.5
+    pub center_pos: f64,
+    pub usage: RfUse,
+    /// If set, a slice select gradient and its rephaser are created for a
+    /// slice of this thickness. Unit: `[m]`
+    pub slice_thickness: Option<f64>,
+}
+
+impl Default for PulseSpec {
+    fn default() -> Self {
+        Self {
+            delay: 0.0,
+            freq_offset: 0.0,
+            phase_offset: 0.0,
+            time_bw_product: 4.0,
+            apodization: 0.0,
+            center_pos: 0.5,
+            usage: RfUse::Undefined,
+            slice_thickness: None,
+        }
+    }
+}
+
+/// An RF pulse together with its slice selection, if requested
+#[derive(Debug, Clone)]
+pub struct Pulse {
+    pub rf: Rf,
+    /// Slice select gradient; the pulse starts after its rise time
+    pub gz: Option<Gradient>,
+    /// Refocuses the slice select gradient from the pulse center on, to be
+    /// played in the following block
+    pub gz_rephase: Option<Gradient>,
+}
+
+/// Creates a sinc pulse with `time_bw_product / 2` zero crossings on either
+/// side of the center. Unit of `flip_angle`: `[rad]`, `duration`: `[s]`
+pub fn make_sinc_pulse(
+    flip_angle: f64,
+    duration: f64,
+    spec: &PulseSpec,
+    system: &System,
+    raster: &TimeRaster,
+) -> Result<Pulse, MakeError> {
+    let bandwidth = spec.time_bw_product / duration;
+    make_pulse(flip_angle, duration, spec, system, raster, |t| {
+        let x = PI * bandwidth * t;
+        if x == 0.0 {
+            1.0
+        } else {
+            x.sin() / x
+        }
+    })
+}
+
+/// Creates a gaussian pulse with a bandwidth of `time_bw_product / duration`.
+/// Unit of `flip_angle`: `[rad]`, `duration`: `[s]`
+pub fn make_gauss_pulse(
+    flip_angle: f64,
+    duration: f64,
+    spec: &PulseSpec,
+    system: &System,
+    raster: &TimeRaster,
+) -> Result<Pulse, MakeError> {
+    let bandwidth = spec.time_bw_product / duration;
+    make_pulse(flip_angle, duration, spec, system, raster, |t| {
+        (-PI * (bandwidth * t).powi(2)).exp()
+    })
+}
+
+/// Creates a rectangular pulse, `apodization` is ignored.
+/// Unit of `flip_angle`: `[rad]`, `duration`: `[s]`
+pub fn make_block_pulse(
+    flip_angle: f64,
+    duration: f64,
+    spec: &PulseSpec,
+    system: &System,
+    raster: &TimeRaster,
+) -> Result<Pulse, MakeError> {
+    let spec = PulseSpec {
+        apodization: 0.0,
+        ..spec.clone()
+    };
+    make_pulse(flip_angle, duration, &spec, system, raster, |_| 1.0)
+}
+
+/// Samples `shape(t)`, with `t` relative to the pulse center, at the centers
+/// of the RF raster intervals and scales it to the flip angle.
+fn make_pulse(
+    flip_angle: f64,
+    duration: f64,
+    spec: &PulseSpec,
+    system: &System,
+    raster: &TimeRaster,
+    shape: impl Fn(f64) -> f64,
+) -> Result<Pulse, MakeError> {
+    let dt = raster.rf;
+    let count = (duration / dt).round();
+    if count < 1.0 || (count - duration / dt).abs() > TOLERANCE {
+        return Err(MakeError::NotOnRaster {
+            what: "Pulse duration",
+            value: duration,
+            raster: dt,
+        });
+    }
+    let center = duration * spec.center_pos;
+
+    let mut signal: Vec<_> = (0..count as usize)
+        .map(|i| {
+            let t = (i as f64 + 0.5) * dt - center;
+            let window = 1.0 - spec.apodization + spec.apodization * (TAU * t / duration).cos();
+            window * shape(t)
+        })
+        .collect();
+    let flip = signal.iter().sum::<f64>() * dt * TAU;
+    // Compared to a block pulse with the peak amplitude of the shape
+    let peak = signal.iter().fold(0.0f64, |max, x| max.max(x.abs()));
+    if flip.abs() <= TOLERANCE * peak * duration * TAU {
+        return Err(MakeError::ZeroIntegral);
+    }
+    for x in &mut signal {
+        *x *= flip_angle / flip;
+    }
+
+    let mut delay = spec.delay.max(system.rf_dead_time);
+    let mut gradients = None;
+
+    if let Some(thickness) = spec.slice_thickness {
+        let bandwidth = spec.time_bw_product / duration;
+        let amplitude = bandwidth / thickness;
+        let mut gz_spec = TrapezoidSpec {
+            amplitude: Some(amplitude),
+            flat_time: Some(duration),
+            ..Default::default()
+        };
+        let gz = make_trapezoid(&gz_spec, system, raster)?;
+        let Gradient::Trap { rise, fall, .. } = gz else {
+            unreachable!("make_trapezoid returns trapezoids");
+        };
+
+        // The pulse plays on the flat top; delay the gradient if the pulse
+        // needs more time than the ramp, otherwise delay the pulse
+        if delay > rise {
+            gz_spec.delay = ceil_raster(delay - rise, raster.grad);
+        }
+        delay = delay.max(rise + gz_spec.delay);
+        let gz = make_trapezoid(&gz_spec, system, raster)?;
+
+        let rephase = TrapezoidSpec {
+            area: Some(-amplitude * (duration * (1.0 - spec.center_pos) + fall / 2.0)),
+            ..Default::default()
+        };
+        gradients = Some((gz, make_trapezoid(&rephase, system, raster)?));
+    }
+
+    let mut rf = Rf::from_real(&signal, delay);
+    rf.freq = spec.freq_offset;
+    rf.phase = spec.phase_offset;
+    rf.center = Some(center);
+    rf.usage = spec.usage;
+
+    let (gz, gz_rephase) = gradients.unzip();
+    Ok(Pulse { rf, gz, gz_rephase })
+}
+
+/// Creates an ADC with `num` samples. The `dwell` time must be on the ADC
+/// raster, the delay is raised to `System::adc_dead_time` if shorter.
+/// Unit of `dwell` and `delay`: `[s]`
+pub fn make_adc(
+    num: u32,
+    dwell: f64,
+    delay: f64,
+    system: &System,
+    raster: &TimeRaster,
+) -> Result<Adc, MakeError> {
+    let x = dwell / raster.adc;
+    if dwell <= 0.0 || (x.round() - x).abs() > TOLERANCE {
+        return Err(MakeError::NotOnRaster {
+            what: "Dwell time",
+            value: dwell,
+            raster: raster.adc,
+        });
+    }
+    Ok(Adc::new(num, dwell, delay.max(system.adc_dead_time)))
+}
+
+impl Gradient {
+    /// Total area of the waveform, unit: `[1/m]`
+    pub fn area(&self, grad_raster: f64) -> f64 {
+        self.breakpoints(grad_raster)
+            .windows(2)
+            .map(|w| 0.5 * (w[0].1 + w[1].1) * (w[1].0 - w[0].0))
+            .sum()
+    }
+}
+
+fn with_first_last(mut grad: Gradient, new_first: f64, new_last: f64) -> Gradient {
+    if let Gradient::Free { first, last, .. } = &mut grad {
+        *first = Some(new_first);
+        *last = Some(new_last);
+    }
+    grad
+}
+
+/// Rounds up to the raster, ignoring rounding errors
+fn ceil_raster(t: f64, raster: f64) -> f64 {
+    (t / raster - TOLERANCE).ceil() * raster
+}
+
+fn check_amplitude(amp: f64, system: &System) -> Result<(), MakeError> {
+    if amp.abs() > system.max_grad * (1.0 + TOLERANCE) {
+        Err(MakeError::GradientAmplitude {
+            amp,
+            max: system.max_grad,
+        })
+    } else {
+        Ok(())
+    }
+}
+
+fn check_slew(slew: f64, system: &System) -> Result<(), MakeError> {
+    if slew.abs() > system.max_slew * (1.0 + TOLERANCE) {
+        Err(MakeError::SlewRate {
+            slew,
+            max: system.max_slew,
+        })
+    } else {
+        Ok(())
+    }
+}
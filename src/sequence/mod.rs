@@ -2,91 +2,288 @@
 use std::{collections::HashMap, path::Path, sync::Arc};
 
 use crate::{
-    error::{self, EventType, ValidationError},
+    error::{self, EventType, ValidationError, Warning},
     parse_file::{self, Section},
 };
 
+mod builder;
+mod complex;
+mod continuity;
 mod display;
+mod extension_handler;
 pub mod from_raw;
+mod kspace;
+mod labels;
+mod make;
+mod raster;
+mod rf_analysis;
+mod rf_power;
+mod rotation;
+mod sample;
+#[cfg(feature = "serde")]
+mod serialize;
+mod signature;
+mod soft_delay;
+mod system;
+mod timeline;
+mod trigger;
+mod write_seq;
 
+pub use builder::SequenceBuilder;
+pub(crate) use complex::Complex;
+pub use extension_handler::{ExtensionHandler, ExtensionHandlers, ExtensionValue};
+pub use kspace::KSpace;
+pub use labels::{Label, LabelName, LabelOp, LabelState};
+pub use make::{
+    make_adc, make_arbitrary_grad, make_block_pulse, make_extended_trapezoid, make_gauss_pulse,
+    make_sinc_pulse, make_trapezoid, Pulse, PulseSpec, TrapezoidSpec,
+};
+pub use rf_power::RfPower;
+pub use rotation::Rotation;
+pub use sample::Sample;
+pub use signature::Signature;
+pub use soft_delay::SoftDelay;
+pub use system::{System, GAMMA};
+pub use timeline::{Event, TimedEvent, Timeline};
+pub use trigger::{Trigger, TriggerType};
+
+#[derive(Debug, Clone)]
 pub struct Sequence {
     pub time_raster: TimeRaster,
     pub name: Option<String>,
     pub fov: Option<(f64, f64, f64)>,
     pub definitions: HashMap<String, String>,
     pub blocks: Vec<Block>,
+    pub signature: Option<Signature>,
+    /// Sections that are not interpreted by pulseq-rs, in file order
+    pub unknown_sections: Vec<UnknownSection>,
+}
+
+/// A section that pulseq-rs does not know, e.g. vendor specific or from a
+/// newer pulseq version. Its content is kept so it can be handled by users.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct UnknownSection {
+    /// Name of the section, without the brackets
+    pub name: String,
+    /// Content of the section without empty lines and comments
+    pub lines: Vec<String>,
 }
 
 impl Sequence {
+    /// Converts and validates parsed sections. The source is not known
+    /// here, so errors are not wrapped in `Error::Located`.
     pub fn from_parsed_file(sections: Vec<Section>) -> Result<Self, error::Error> {
-        let tmp = from_raw::from_raw(sections)?;
+        let tmp = from_raw::from_raw(sections).map_err(error::ConversionError::without_line)?;
         tmp.validate()?;
         Ok(tmp)
     }
 
+    /// Loads the sequence and checks its signature, if it has one.
+    /// The result is stored in `signature`, a mismatch is not an error.
+    /// Conversion and validation errors are wrapped in `Error::Located`,
+    /// pointing to the line of the source that caused them; match on
+    /// `Error::inner` to handle them by their cause.
     pub fn from_source(source: &str) -> Result<Self, error::Error> {
-        Self::from_parsed_file(parse_file::parse_file(source)?)
+        Self::load(source, None, &LoadOptions::default()).map(|(seq, _)| seq)
     }
 
     pub fn from_file<P: AsRef<Path>>(path: P) -> Result<Self, error::Error> {
+        let source = std::fs::read_to_string(&path)?;
+        Self::load(&source, Some(path.as_ref()), &LoadOptions::default()).map(|(seq, _)| seq)
+    }
+
+    /// Like `from_source`, but returns the problems that were recovered from
+    /// in lenient mode. In strict mode, the list of warnings is empty.
+    pub fn from_source_with_options(
+        source: &str,
+        options: &LoadOptions,
+    ) -> Result<(Self, Vec<Warning>), error::Error> {
+        Self::load(source, None, options)
+    }
+
+    pub fn from_file_with_options<P: AsRef<Path>>(
+        path: P,
+        options: &LoadOptions,
+    ) -> Result<(Self, Vec<Warning>), error::Error> {
+        let source = std::fs::read_to_string(&path)?;
+        Self::load(&source, Some(path.as_ref()), options)
+    }
+
+    fn load(
+        source: &str,
+        path: Option<&Path>,
+        options: &LoadOptions,
+    ) -> Result<(Self, Vec<Warning>), error::Error> {
+        let mut diag = from_raw::Diagnostics::new(options.lenient);
+
+        let sections = parse_file::parse_file(source)?;
+
+        // Validation errors only know the block ID; IDs should be unique but
+        // this is not enforced, so report the first block with that ID.
+        let mut block_lines = HashMap::new();
+        for section in &sections {
+            if let Section::Blocks(blocks) = section {
+                for block in blocks {
+                    block_lines.entry(block.id).or_insert(block.line);
+                }
+            }
+        }
+
+        let mut seq = match from_raw::from_raw_with(sections, &mut diag, &options.extensions) {
+            Ok(seq) => seq,
+            Err(error::ConversionError::AtLine { line, error }) => {
+                let field = match *error {
+                    error::ConversionError::BrokenRef { ty, .. } => Some(block_field(ty)),
+                    _ => None,
+                };
+                return Err(error::Error::Located {
+                    location: error::Location::new(source, path, line, field),
+                    error: Box::new(error::Error::ConversionError(*error)),
+                });
+            }
+            Err(err) => return Err(err.into()),
+        };
+        if options.lenient {
+            let errors = seq.validation_errors();
+            diag.warnings
+                .extend(errors.into_iter().map(Warning::Validation));
+        } else if let Err(err) = seq.validate() {
+            return Err(match block_lines.get(&err.block_id()) {
+                Some(&line) if line > 0 => {
+                    let field = err.event_type().map_or(1, block_field);
+                    error::Error::Located {
+                        location: error::Location::new(source, path, line, Some(field)),
+                        error: Box::new(err.into()),
+                    }
+                }
+                _ => err.into(),
+            });
+        }
+
+        if let Some(signature) = &mut seq.signature {
+            signature.verify(source);
+        }
+        Ok((seq, diag.warnings))
+    }
+
+    /// Like `from_source`, but fails if the sequence is not signed or if the
+    /// signature does not match its content, e.g. because it was edited.
+    pub fn from_source_strict(source: &str) -> Result<Self, error::Error> {
+        let seq = Self::from_source(source)?;
+        seq.signature
+            .as_ref()
+            .ok_or(error::SignatureError::Missing)?
+            .check()?;
+        Ok(seq)
+    }
+
+    pub fn from_file_strict<P: AsRef<Path>>(path: P) -> Result<Self, error::Error> {
         let source = std::fs::read_to_string(path)?;
-        Self::from_source(&source)
+        Self::from_source_strict(&source)
     }
 
     pub fn validate(&self) -> Result<(), error::ValidationError> {
+        match self.validation_errors().into_iter().next() {
+            Some(err) => Err(err),
+            None => Ok(()),
+        }
+    }
+
+    /// Returns every problem found by `validate`, which only reports the first
+    pub fn validation_errors(&self) -> Vec<error::ValidationError> {
         // NOTE: We could check if block IDs are in some order or at least not
         // duplicated, but as they are never really used, this might be too strict
+        let mut errors = Vec::new();
 
         // Check if no event is longer than the duration of its block
         for block in &self.blocks {
-            // Passes through dur if its Some(..) and more than block.duration
-            let check = |dur: Option<f64>, ty: EventType| {
-                dur.map_or(Ok(()), |dur| {
-                    if dur > block.duration + f64::EPSILON {
-                        Err(ValidationError::EventTooLong {
-                            ty,
-                            block_id: block.id,
-                            dur,
-                            block_dur: block.duration,
-                        })
-                    } else {
-                        Ok(())
-                    }
-                })
-            };
             let grad_raster = self.time_raster.grad;
+            let durations = [
+                (
+                    block.rf.as_ref().map(|rf| rf.duration(self.time_raster.rf)),
+                    EventType::Rf,
+                ),
+                (
+                    block.gx.as_ref().map(|gx| gx.duration(grad_raster)),
+                    EventType::Gx,
+                ),
+                (
+                    block.gy.as_ref().map(|gy| gy.duration(grad_raster)),
+                    EventType::Gy,
+                ),
+                (
+                    block.gz.as_ref().map(|gz| gz.duration(grad_raster)),
+                    EventType::Gz,
+                ),
+                (block.adc.as_ref().map(|adc| adc.duration()), EventType::Adc),
+            ]
+            .into_iter()
+            .chain(
+                (block.triggers.iter())
+                    .map(|trigger| (Some(trigger.total_duration()), EventType::Trigger)),
+            );
 
-            check(
-                block.rf.as_ref().map(|rf| rf.duration(self.time_raster.rf)),
-                EventType::Rf,
-            )?;
-            check(
-                block.gx.as_ref().map(|gx| gx.duration(grad_raster)),
-                EventType::Gx,
-            )?;
-            check(
-                block.gy.as_ref().map(|gy| gy.duration(grad_raster)),
-                EventType::Gy,
-            )?;
-            check(
-                block.gz.as_ref().map(|gz| gz.duration(grad_raster)),
-                EventType::Gz,
-            )?;
-            check(block.adc.as_ref().map(|adc| adc.duration()), EventType::Adc)?;
+            for (dur, ty) in durations {
+                if let Some(dur) = dur.filter(|&dur| dur > block.duration + f64::EPSILON) {
+                    errors.push(ValidationError::EventTooLong {
+                        ty,
+                        block_id: block.id,
+                        dur,
+                        block_dur: block.duration,
+                    });
+                }
+            }
         }
 
         // Check things like identical shape size and no negative times
         for block in &self.blocks {
             let id = block.id;
             use EventType::*;
-            block.rf.as_ref().map_or(Ok(()), |x| x.validate(id))?;
-            block.gx.as_ref().map_or(Ok(()), |x| x.validate(Gx, id))?;
-            block.gy.as_ref().map_or(Ok(()), |x| x.validate(Gy, id))?;
-            block.gz.as_ref().map_or(Ok(()), |x| x.validate(Gz, id))?;
-            block.adc.as_ref().map_or(Ok(()), |x| x.validate(id))?;
+            let results = [
+                block.rf.as_ref().map(|x| x.validate(id)),
+                block.gx.as_ref().map(|x| x.validate(Gx, id)),
+                block.gy.as_ref().map(|x| x.validate(Gy, id)),
+                block.gz.as_ref().map(|x| x.validate(Gz, id)),
+                block.adc.as_ref().map(|x| x.validate(id)),
+            ]
+            .into_iter()
+            .flatten()
+            .chain(block.triggers.iter().map(|trigger| trigger.validate(id)));
+            errors.extend(results.filter_map(Result::err));
         }
 
-        Ok(())
+        errors
+    }
+}
+
+/// Options for `Sequence::from_source_with_options`
+#[derive(Debug, Clone, Default)]
+pub struct LoadOptions {
+    /// Recover from problems instead of failing: the first of duplicate IDs
+    /// and definitions is used, missing or unparsable definitions fall back
+    /// to defaults and validation errors are reported as warnings. Syntax
+    /// errors and broken references are still errors. Unknown sections,
+    /// which are never an error, are reported as well.
+    pub lenient: bool,
+    /// Handlers for extensions that are not interpreted by pulseq-rs
+    pub extensions: ExtensionHandlers,
+}
+
+impl LoadOptions {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn lenient(mut self, lenient: bool) -> Self {
+        self.lenient = lenient;
+        self
+    }
+
+    /// Registers a handler for the extension with the name `handler.name()`
+    pub fn extension<H: ExtensionHandler>(mut self, handler: H) -> Self {
+        self.extensions.register(handler);
+        self
     }
 }
 
@@ -94,6 +291,8 @@ impl Sequence {
 /// RF and gradient shapes rely on a time raster! We solve this by always
 /// providing the following definitions, filling them with the default
 /// values of the Siemens interpreter if not provided in pre 1.4 sequences.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct TimeRaster {
     pub grad: f64,
     pub rf: f64,
@@ -112,6 +311,20 @@ impl Default for TimeRaster {
     }
 }
 
+/// Index of the column of a [BLOCKS] entry that references the event type
+fn block_field(ty: EventType) -> usize {
+    match ty {
+        EventType::Delay => 1,
+        EventType::Rf => 2,
+        EventType::Gx => 3,
+        EventType::Gy => 4,
+        EventType::Gz => 5,
+        EventType::Adc => 6,
+        EventType::Trigger | EventType::Extension => 7,
+    }
+}
+
+#[derive(Debug, Clone, Default)]
 pub struct Block {
     /// Blocks are stored in a simple vector, instead of a HashMap with their ID
     /// as value, because they are not referenced but executed top to bottom.
@@ -123,9 +336,24 @@ pub struct Block {
     pub gy: Option<Arc<Gradient>>,
     pub gz: Option<Arc<Gradient>>,
     pub adc: Option<Arc<Adc>>,
+    /// Label operations (LABELSET and LABELINC extensions), in the order of
+    /// the extension list.
+    pub labels: Vec<Label>,
+    /// Physio and output triggers (TRIGGERS extension)
+    pub triggers: Vec<Arc<Trigger>>,
+    /// Rotation of the gradients (ROTATIONS extension)
+    pub rotation: Option<Arc<Rotation>>,
+    /// Adjustable block duration (DELAYS extension)
+    pub soft_delay: Option<Arc<SoftDelay>>,
+    /// Extensions of this block that are not interpreted by pulseq-rs, in the
+    /// order of their linked list.
+    pub extensions: Vec<Arc<Extension>>,
 }
 
+#[derive(Debug, Clone)]
 pub struct Rf {
+    /// ID in the [RF] section of the file this event was loaded from
+    pub id: Option<u32>,
     /// Unit: `[Hz]`
     pub amp: f64,
     /// Unit: `[rad]`
@@ -134,6 +362,14 @@ pub struct Rf {
     pub delay: f64,
     /// Unit: `[Hz]`
     pub freq: f64,
+    /// Unit: `[ppm]`
+    pub freq_ppm: f64,
+    /// Unit: `[rad/MHz]`
+    pub phase_ppm: f64,
+    /// Unit: `[s]`, relative to the start of the shape (excluding the delay).
+    /// Only stored in the file since pulseq 1.5.
+    pub center: Option<f64>,
+    pub usage: RfUse,
     // Shapes
     pub amp_shape: Arc<Shape>,
     pub phase_shape: Arc<Shape>,
@@ -141,16 +377,40 @@ pub struct Rf {
     pub shim_shape: Option<(Arc<Shape>, Arc<Shape>)>,
 }
 
+/// The use of an RF pulse, as stored since pulseq 1.5
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum RfUse {
+    Excitation,
+    Refocusing,
+    Inversion,
+    Saturation,
+    Preparation,
+    Other,
+    Undefined,
+}
+
+#[derive(Debug, Clone)]
 pub enum Gradient {
     Free {
+        /// ID in the [GRADIENTS] section of the file this event was loaded from
+        id: Option<u32>,
         /// Unit: `[Hz/m]`
         amp: f64,
+        /// Unit: `[Hz/m]`, amplitude at the start of the gradient.
+        /// Only stored in the file since pulseq 1.5.
+        first: Option<f64>,
+        /// Unit: `[Hz/m]`, amplitude at the end of the gradient.
+        /// Only stored in the file since pulseq 1.5.
+        last: Option<f64>,
         /// Unit: `[s]`
         delay: f64,
         // Shapes
         shape: Arc<Shape>,
     },
     Trap {
+        /// ID in the [TRAP] section of the file this event was loaded from
+        id: Option<u32>,
         /// Unit: `[Hz/m]`
         amp: f64,
         /// Unit: `[s]`
@@ -164,7 +424,10 @@ pub enum Gradient {
     },
 }
 
+#[derive(Debug, Clone)]
 pub struct Adc {
+    /// ID in the [ADC] section of the file this event was loaded from
+    pub id: Option<u32>,
     pub num: u32,
     /// Unit: `[s]`
     pub dwell: f64,
@@ -174,24 +437,49 @@ pub struct Adc {
     pub freq: f64,
     /// Unit: `[rad]`
     pub phase: f64,
+    /// Unit: `[ppm]`
+    pub freq_ppm: f64,
+    /// Unit: `[rad/MHz]`
+    pub phase_ppm: f64,
+    /// Phase modulation during the ADC (since pulseq 1.5)
+    pub phase_shape: Option<Arc<Shape>>,
 }
 
-pub struct Shape(pub Vec<f64>);
+/// An instance of an extension, as referenced by a block. The data is not
+/// interpreted, as the format depends on the extension.
+#[derive(Debug, Clone)]
+pub struct Extension {
+    /// Name of the extension, e.g. `LABELSET` or `TRIGGERS`
+    pub name: String,
+    /// The data of the extension object, without its ID
+    pub data: String,
+    /// Parsed data, if an `ExtensionHandler` was registered for this extension
+    pub value: Option<ExtensionValue>,
+}
+
+#[derive(Debug, Clone)]
+pub struct Shape {
+    pub samples: Vec<f64>,
+    /// ID in the [SHAPES] section of the file this shape was loaded from
+    pub id: Option<u32>,
+    /// ID of the time shape that was used to expand this shape
+    pub time_id: Option<u32>,
+}
 
 // Helper functions and other impls
 
 impl Rf {
     pub fn duration(&self, rf_raster: f64) -> f64 {
-        self.delay + self.amp_shape.0.len() as f64 * rf_raster
+        self.delay + self.amp_shape.samples.len() as f64 * rf_raster
     }
 
     fn validate(&self, block_id: u32) -> Result<(), error::ValidationError> {
-        if self.phase_shape.0.len() != self.amp_shape.0.len() {
+        if self.phase_shape.samples.len() != self.amp_shape.samples.len() {
             Err(ValidationError::ShapeMismatch {
                 ty: EventType::Rf,
                 block_id,
-                length_1: self.phase_shape.0.len(),
-                length_2: self.amp_shape.0.len(),
+                length_1: self.phase_shape.samples.len(),
+                length_2: self.amp_shape.samples.len(),
             })?;
         }
         Ok(())
@@ -201,7 +489,7 @@ impl Rf {
 impl Gradient {
     pub fn duration(&self, grad_raster: f64) -> f64 {
         match self {
-            Gradient::Free { shape, delay, .. } => delay + shape.0.len() as f64 * grad_raster,
+            Gradient::Free { shape, delay, .. } => delay + shape.samples.len() as f64 * grad_raster,
             Gradient::Trap {
                 rise,
                 flat,
@@ -219,6 +507,37 @@ impl Gradient {
         }
     }
 
+    /// ID in the file this gradient was loaded from. Free and trapezoid
+    /// gradients share their IDs.
+    pub fn id(&self) -> Option<u32> {
+        match self {
+            Gradient::Free { id, .. } => *id,
+            Gradient::Trap { id, .. } => *id,
+        }
+    }
+
+    /// Amplitude at the start of the gradient (after the delay). If not
+    /// known, free gradients are assumed to start at their first sample.
+    pub fn first(&self) -> f64 {
+        match self {
+            Gradient::Free {
+                amp, first, shape, ..
+            } => first.unwrap_or(amp * shape.samples.first().unwrap_or(&0.0)),
+            Gradient::Trap { .. } => 0.0,
+        }
+    }
+
+    /// Amplitude at the end of the gradient. If not known, free gradients
+    /// are assumed to end at their last sample.
+    pub fn last(&self) -> f64 {
+        match self {
+            Gradient::Free {
+                amp, last, shape, ..
+            } => last.unwrap_or(amp * shape.samples.last().unwrap_or(&0.0)),
+            Gradient::Trap { .. } => 0.0,
+        }
+    }
+
     fn validate(&self, ty: EventType, block_id: u32) -> Result<(), error::ValidationError> {
         match self {
             Gradient::Free { delay, .. } => {
@@ -0,0 +1,74 @@
+// Validation of all timings against the time raster
+use super::*;
+
+impl Sequence {
+    /// Checks that block durations and event timings are integer multiples
+    /// of their raster: RF delays of `TimeRaster::rf`, gradient delays and
+    /// trap ramp / flat times of `grad`, ADC delays and dwell times of `adc`.
+    /// `tolerance` is the allowed deviation, as fraction of the raster.
+    /// Returns every timing that is off the raster.
+    pub fn check_raster(&self, tolerance: f64) -> Vec<ValidationError> {
+        let raster = &self.time_raster;
+        let mut errors = Vec::new();
+
+        for block in &self.blocks {
+            let block_id = block.id;
+            if !on_raster(block.duration, raster.block, tolerance) {
+                errors.push(ValidationError::BlockNotOnRaster {
+                    block_id,
+                    duration: block.duration,
+                    raster: raster.block,
+                });
+            }
+
+            let mut check = |ty: EventType, timing: &'static str, value: f64, raster: f64| {
+                if !on_raster(value, raster, tolerance) {
+                    errors.push(ValidationError::EventNotOnRaster {
+                        ty,
+                        block_id,
+                        timing,
+                        value,
+                        raster,
+                    });
+                }
+            };
+
+            if let Some(rf) = &block.rf {
+                check(EventType::Rf, "delay", rf.delay, raster.rf);
+            }
+            for (grad, ty) in [
+                (&block.gx, EventType::Gx),
+                (&block.gy, EventType::Gy),
+                (&block.gz, EventType::Gz),
+            ] {
+                match grad.as_deref() {
+                    Some(Gradient::Free { delay, .. }) => check(ty, "delay", *delay, raster.grad),
+                    Some(Gradient::Trap {
+                        rise,
+                        flat,
+                        fall,
+                        delay,
+                        ..
+                    }) => {
+                        check(ty, "delay", *delay, raster.grad);
+                        check(ty, "rise", *rise, raster.grad);
+                        check(ty, "flat", *flat, raster.grad);
+                        check(ty, "fall", *fall, raster.grad);
+                    }
+                    None => (),
+                }
+            }
+            if let Some(adc) = &block.adc {
+                check(EventType::Adc, "delay", adc.delay, raster.adc);
+                check(EventType::Adc, "dwell", adc.dwell, raster.adc);
+            }
+        }
+
+        errors
+    }
+}
+
+fn on_raster(value: f64, raster: f64, tolerance: f64) -> bool {
+    let steps = value / raster;
+    (steps - steps.round()).abs() <= tolerance
+}
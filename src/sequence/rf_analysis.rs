@@ -0,0 +1,137 @@
+// Properties of RF pulses derived from their waveform, like pypulseq's
+// calc_rf_bandwidth and calc_rf_center
+use std::f64::consts::TAU;
+
+use super::*;
+
+impl Rf {
+    /// Nominal flip angle: magnitude of the integral over the complex
+    /// waveform, so side lobes with a phase of pi count negative.
+    /// Unit: `[rad]`
+    pub fn flip_angle(&self, rf_raster: f64) -> f64 {
+        TAU * self.integral(rf_raster).abs()
+    }
+
+    /// Time of the peak magnitude relative to the start of the shape: the
+    /// middle of all samples within 0.001 % of the maximum. Unit: `[s]`
+    pub fn peak_time(&self, rf_raster: f64) -> f64 {
+        let shape = &self.amp_shape.samples;
+        let max = shape.iter().fold(0.0f64, |max, x| max.max(x.abs()));
+        let first = shape.iter().position(|x| x.abs() >= max * 0.99999);
+        let last = shape.iter().rposition(|x| x.abs() >= max * 0.99999);
+        match (first, last) {
+            (Some(first), Some(last)) => (first + last + 1) as f64 * 0.5 * rf_raster,
+            _ => 0.0,
+        }
+    }
+
+    /// Time from the pulse center (`center` or the peak, if not stored) to
+    /// the end of the pulse. Unit: `[s]`
+    pub fn isodelay(&self, rf_raster: f64) -> f64 {
+        self.amp_shape.samples.len() as f64 * rf_raster - self.center_or_peak(rf_raster)
+    }
+
+    /// Peak amplitude of the waveform, divide by `GAMMA` to get `[T]`.
+    /// Unit: `[Hz]`
+    pub fn peak_b1(&self) -> f64 {
+        let max = (self.amp_shape.samples.iter()).fold(0.0f64, |max, x| max.max(x.abs()));
+        self.amp.abs() * max
+    }
+
+    /// Integral of the squared magnitude, divide by `GAMMA²` to get
+    /// `[T²s]`. Unit: `[Hz²s]`
+    pub fn energy(&self, rf_raster: f64) -> f64 {
+        let sum: f64 = self.amp_shape.samples.iter().map(|x| x * x).sum();
+        self.amp * self.amp * sum * rf_raster
+    }
+
+    /// Full width at half maximum of the magnitude spectrum of the waveform,
+    /// which is the excited bandwidth in the small tip angle approximation.
+    /// The spectrum is calculated by a zero padded FFT, with the half maximum
+    /// crossings interpolated between frequency bins. A pulse without
+    /// amplitude has no bandwidth. Unit: `[Hz]`
+    pub fn bandwidth(&self, rf_raster: f64) -> f64 {
+        let signal = self.waveform();
+        if signal.is_empty() {
+            return 0.0;
+        }
+        let len = (signal.len() * 32).max(4096).next_power_of_two();
+        let mut spectrum = signal;
+        spectrum.resize(len, Complex::default());
+        fft(&mut spectrum);
+        let magnitude: Vec<f64> = spectrum.iter().map(|x| x.abs()).collect();
+
+        let (peak, max) = (magnitude.iter().copied().enumerate())
+            .max_by(|a, b| a.1.total_cmp(&b.1))
+            .unwrap();
+        if max == 0.0 {
+            return 0.0;
+        }
+        let half = 0.5 * max;
+        // Distance in bins from the peak to the half maximum, walking in
+        // one direction; the spectrum is periodic
+        let width = |step: isize| {
+            let at = |i: usize| {
+                magnitude[(peak as isize + step * i as isize).rem_euclid(len as isize) as usize]
+            };
+            let i = (1..len).find(|&i| at(i) < half).unwrap_or(len);
+            let (a, b) = (at(i - 1), at(i));
+            (i - 1) as f64 + (a - half) / (a - b)
+        };
+        (width(1) + width(-1)) / (len as f64 * rf_raster)
+    }
+
+    /// Product of `bandwidth` and pulse duration, without unit
+    pub fn time_bandwidth_product(&self, rf_raster: f64) -> f64 {
+        self.bandwidth(rf_raster) * self.amp_shape.samples.len() as f64 * rf_raster
+    }
+
+    /// Integral of the complex waveform without the phase offset, unit: `[1]`
+    pub(crate) fn integral(&self, rf_raster: f64) -> Complex {
+        (self.waveform().into_iter())
+            .fold(Complex::default(), Complex::add)
+            .scale(rf_raster)
+    }
+
+    /// Complex samples in `[Hz]`, without phase and frequency offset
+    fn waveform(&self) -> Vec<Complex> {
+        let phases = (self.phase_shape.samples.iter()).chain(std::iter::repeat(&0.0));
+        (self.amp_shape.samples.iter().zip(phases))
+            .map(|(mag, phase)| Complex::polar(self.amp * mag, TAU * phase))
+            .collect()
+    }
+}
+
+/// In-place radix-2 FFT, `data.len()` must be a power of two
+pub(crate) fn fft(data: &mut [Complex]) {
+    let n = data.len();
+    assert!(n.is_power_of_two());
+    if n < 2 {
+        return;
+    }
+
+    // Bit reversal permutation
+    let bits = n.trailing_zeros();
+    for i in 0..n {
+        let j = i.reverse_bits() >> (usize::BITS - bits);
+        if i < j {
+            data.swap(i, j);
+        }
+    }
+
+    let mut size = 2;
+    while size <= n {
+        let step = Complex::polar(1.0, -TAU / size as f64);
+        for start in (0..n).step_by(size) {
+            let mut w = Complex::polar(1.0, 0.0);
+            for i in 0..size / 2 {
+                let a = data[start + i];
+                let b = data[start + i + size / 2].mul(w);
+                data[start + i] = a.add(b);
+                data[start + i + size / 2] = a.add(b.scale(-1.0));
+                w = w.mul(step);
+            }
+        }
+        size *= 2;
+    }
+}
@@ -0,0 +1,77 @@
+// RF energy and power for SAR estimation, similar to pypulseq's calc_SAR
+
+use super::*;
+
+/// RF energy and power of a sequence, scaled from `|B1|²` by the factor
+/// passed to `Sequence::rf_power`. With a scaling in `[W/T²]` (e.g. from a
+/// coil and body model), the units are the ones given below; with `[W/kg/T²]`
+/// the powers are SAR values in `[W/kg]`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct RfPower {
+    /// Energy of all RF pulses. Unit: `[J]`
+    pub energy: f64,
+    /// Highest instantaneous power of all pulses. Unit: `[W]`
+    pub peak_power: f64,
+    /// Highest average power over 10 s. Unit: `[W]`
+    pub average_10s: f64,
+    /// Highest average power over 6 min. Unit: `[W]`
+    pub average_6min: f64,
+}
+
+impl Sequence {
+    /// Integrates `scaling * |B1|²` over all RF pulses, with B1 in `[T]`.
+    ///
+    /// For the time averages, the energy of every pulse is assigned to its
+    /// start and the maximum over all windows starting at a pulse is taken.
+    /// Sequences shorter than a window are assumed to be repeated, so their
+    /// average is the energy divided by the sequence duration.
+    pub fn rf_power(&self, scaling: f64) -> RfPower {
+        let timeline = self.timeline();
+        let raster = self.time_raster.rf;
+        let to_tesla = 1.0 / (GAMMA * GAMMA);
+
+        let mut pulses = Vec::new();
+        let mut peak_power = 0.0f64;
+        for (index, block) in self.blocks.iter().enumerate() {
+            if let Some(rf) = &block.rf {
+                let t = timeline.block_start(index) + rf.delay;
+                pulses.push((t, scaling * to_tesla * rf.energy(raster)));
+                peak_power = peak_power.max(scaling * to_tesla * rf.peak_b1().powi(2));
+            }
+        }
+
+        let duration = timeline.duration();
+        RfPower {
+            energy: pulses.iter().map(|p| p.1).sum(),
+            peak_power,
+            average_10s: average_power(&pulses, duration, 10.0),
+            average_6min: average_power(&pulses, duration, 360.0),
+        }
+    }
+}
+
+/// Highest average power of the `(start, energy)` pulses, sorted by start,
+/// over a sliding `window`
+fn average_power(pulses: &[(f64, f64)], duration: f64, window: f64) -> f64 {
+    if duration <= window {
+        let energy: f64 = pulses.iter().map(|p| p.1).sum();
+        return if duration > 0.0 {
+            energy / duration
+        } else {
+            0.0
+        };
+    }
+
+    let mut max = 0.0f64;
+    let mut energy = 0.0;
+    let mut end = 0;
+    for (start, &(t, _)) in pulses.iter().enumerate() {
+        while end < pulses.len() && pulses[end].0 < t + window {
+            energy += pulses[end].1;
+            end += 1;
+        }
+        max = max.max(energy);
+        energy -= pulses[start].1;
+    }
+    max / window
+}
@@ -0,0 +1,50 @@
+// Typed representation of the ROTATIONS extension (pulseq 1.5)
+use crate::error::ConversionError;
+
+/// Rotation of all gradients in a block, given as unit quaternion.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Rotation {
+    /// `[w, x, y, z]`
+    pub quaternion: [f64; 4],
+}
+
+impl Rotation {
+    /// Parses the data of a ROTATIONS extension object: `q0 q1 q2 q3`
+    pub(crate) fn parse(data: &str) -> Result<Self, ConversionError> {
+        let err = || ConversionError::InvalidExtensionData {
+            name: "ROTATIONS".to_owned(),
+            data: data.to_owned(),
+        };
+
+        let quaternion: Vec<f64> = data
+            .split_whitespace()
+            .map(|x| x.parse().map_err(|_| err()))
+            .collect::<Result<_, _>>()?;
+        Ok(Self {
+            quaternion: quaternion.try_into().map_err(|_| err())?,
+        })
+    }
+
+    /// Rotation matrix that maps the logical gradient axes onto physical ones
+    pub fn matrix(&self) -> [[f64; 3]; 3] {
+        let [w, x, y, z] = self.quaternion;
+        [
+            [
+                1.0 - 2.0 * (y * y + z * z),
+                2.0 * (x * y - w * z),
+                2.0 * (x * z + w * y),
+            ],
+            [
+                2.0 * (x * y + w * z),
+                1.0 - 2.0 * (x * x + z * z),
+                2.0 * (y * z - w * x),
+            ],
+            [
+                2.0 * (x * z - w * y),
+                2.0 * (y * z + w * x),
+                1.0 - 2.0 * (x * x + y * y),
+            ],
+        ]
+    }
+}
@@ -0,0 +1,213 @@
+// Evaluation of the RF, gradient and ADC channels at arbitrary points in time
+use std::f64::consts::TAU;
+
+use super::system::TOLERANCE;
+use super::*;
+
+/// The state of all channels at a single point in time.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct Sample {
+    /// RF magnitude, unit: `[Hz]`
+    pub rf_amp: f64,
+    /// RF phase, including phase offset, phase shape and frequency offset.
+    /// Unit: `[rad]`
+    pub rf_phase: f64,
+    /// Unit: `[Hz/m]`
+    pub gx: f64,
+    /// Unit: `[Hz/m]`
+    pub gy: f64,
+    /// Unit: `[Hz/m]`
+    pub gz: f64,
+    /// True if an ADC sample window is open at this point in time
+    pub adc: bool,
+}
+
+impl Sample {
+    /// RF amplitude as complex number `(re, im)`, unit: `[Hz]`
+    pub fn rf_complex(&self) -> (f64, f64) {
+        let (sin, cos) = self.rf_phase.sin_cos();
+        (self.rf_amp * cos, self.rf_amp * sin)
+    }
+}
+
+impl Sequence {
+    /// Samples all channels at the absolute time `t` (in `[s]`).
+    /// Outside of the sequence, all channels are zero.
+    ///
+    /// Every call builds the timeline of the whole sequence, use
+    /// `sample_many` to sample more than a few points.
+    pub fn sample(&self, t: f64) -> Sample {
+        self.sample_many(&[t])[0]
+    }
+
+    /// Samples all channels at the given absolute times (in `[s]`), which
+    /// don't need to be sorted. The timeline is built once for all of them.
+    pub fn sample_many(&self, times: &[f64]) -> Vec<Sample> {
+        let timeline = self.timeline();
+
+        times
+            .iter()
+            .map(|&t| {
+                let Some(index) = timeline.block_at(t) else {
+                    return Sample::default();
+                };
+                let block = &self.blocks[index];
+                let t = t - timeline.block_start(index);
+                // The timeline rounds durations up to the block raster
+                if t < block.duration {
+                    block.sample(t, &self.time_raster)
+                } else {
+                    Sample::default()
+                }
+            })
+            .collect()
+    }
+}
+
+impl Block {
+    /// Samples all channels at the time `t`, relative to the block start
+    pub fn sample(&self, t: f64, time_raster: &TimeRaster) -> Sample {
+        let (rf_amp, rf_phase) = self
+            .rf
+            .as_ref()
+            .and_then(|rf| rf.sample(t, time_raster.rf))
+            .unwrap_or_default();
+        let grad =
+            |g: &Option<Arc<Gradient>>| g.as_ref().map_or(0.0, |g| g.sample(t, time_raster.grad));
+
+        Sample {
+            rf_amp,
+            rf_phase,
+            gx: grad(&self.gx),
+            gy: grad(&self.gy),
+            gz: grad(&self.gz),
+            adc: self.adc.as_ref().is_some_and(|adc| adc.is_active(t)),
+        }
+    }
+}
+
+impl Rf {
+    /// Returns the magnitude `[Hz]` and phase `[rad]` at the time `t`,
+    /// relative to the block start, or `None` if the pulse is not playing.
+    /// Shapes are piecewise constant on the RF raster. The frequency offset
+    /// phase is zero at the pulse center if it is known, otherwise at the
+    /// start of the shape. ppm offsets are ignored as they depend on B0.
+    pub fn sample(&self, t: f64, rf_raster: f64) -> Option<(f64, f64)> {
+        let t = t - self.delay;
+        if t < 0.0 {
+            return None;
+        }
+        // Times exactly on the raster belong to the following sample
+        let index = (t / rf_raster + TOLERANCE).floor() as usize;
+        let amp = self.amp_shape.samples.get(index)?;
+        let phase = self.phase_shape.samples.get(index).copied().unwrap_or(0.0);
+
+        let t_ref = t - self.center.unwrap_or(0.0);
+        Some((
+            self.amp * amp,
+            self.phase + TAU * phase + TAU * self.freq * t_ref,
+        ))
+    }
+}
+
+impl Gradient {
+    /// Returns the amplitude `[Hz/m]` at the time `t`, relative to the block
+    /// start. Traps are evaluated exactly; free gradients are linearly
+    /// interpolated between the samples, which sit in the center of their
+    /// raster interval. Before the first / after the last sample, they are
+    /// interpolated to `first` / `last` if known, otherwise held constant.
+    pub fn sample(&self, t: f64, grad_raster: f64) -> f64 {
+        match self {
+            Gradient::Free {
+                amp, delay, shape, ..
+            } => {
+                let samples = &shape.samples;
+                let t = t - delay;
+                if t < 0.0 || t >= samples.len() as f64 * grad_raster {
+                    return 0.0;
+                }
+                // Position on the sample grid
+                let x = t / grad_raster - 0.5;
+                let n = samples.len() - 1;
+
+                if x < 0.0 {
+                    let first = self.first();
+                    first + (x + 0.5) * 2.0 * (amp * samples[0] - first)
+                } else if x >= n as f64 {
+                    amp * samples[n] + (x - n as f64) * 2.0 * (self.last() - amp * samples[n])
+                } else {
+                    let i = x as usize;
+                    let a = samples[i];
+                    let b = samples[(i + 1).min(n)];
+                    amp * (a + (x - i as f64) * (b - a))
+                }
+            }
+            Gradient::Trap {
+                amp,
+                rise,
+                flat,
+                fall,
+                delay,
+                ..
+            } => {
+                let t = t - delay;
+                if t < 0.0 {
+                    0.0
+                } else if t < *rise {
+                    amp * t / rise
+                } else if t < rise + flat {
+                    *amp
+                } else if t < rise + flat + fall {
+                    amp * (rise + flat + fall - t) / fall
+                } else {
+                    0.0
+                }
+            }
+        }
+    }
+
+    /// Returns the corners `(t, amp)` of the piecewise linear waveform that
+    /// `sample` evaluates, with `t` relative to the block start. The waveform
+    /// is zero before the first and after the last point.
+    pub fn breakpoints(&self, grad_raster: f64) -> Vec<(f64, f64)> {
+        match self {
+            Gradient::Free {
+                amp, delay, shape, ..
+            } => {
+                let samples = &shape.samples;
+                if samples.is_empty() {
+                    return Vec::new();
+                }
+                let mut points = Vec::with_capacity(samples.len() + 2);
+                points.push((*delay, self.first()));
+                points.extend(
+                    (samples.iter().enumerate())
+                        .map(|(i, x)| (delay + (i as f64 + 0.5) * grad_raster, amp * x)),
+                );
+                points.push((delay + samples.len() as f64 * grad_raster, self.last()));
+                points
+            }
+            Gradient::Trap {
+                amp,
+                rise,
+                flat,
+                fall,
+                delay,
+                ..
+            } => vec![
+                (*delay, 0.0),
+                (delay + rise, *amp),
+                (delay + rise + flat, *amp),
+                (delay + rise + flat + fall, 0.0),
+            ],
+        }
+    }
+}
+
+impl Adc {
+    /// True if `t`, relative to the block start, lies within the ADC window
+    /// `[delay, delay + num * dwell)`.
+    pub fn is_active(&self, t: f64) -> bool {
+        t >= self.delay && t < self.duration()
+    }
+}
@@ -0,0 +1,512 @@
+// Serialization of the sequence with serde, enabled by the "serde" feature
+use std::collections::BTreeMap;
+
+use serde::{de::Error as _, Deserialize, Deserializer, Serialize, Serializer};
+
+use super::{
+    write_seq::{IdTable, SourceId},
+    *,
+};
+
+/// The serialized form of a sequence. Events and shapes are stored once in
+/// tables keyed by their ID and referenced by the blocks / events using them,
+/// so that sharing (the same `Arc`) survives a round trip. IDs are those of
+/// the .seq file the sequence was loaded from where possible, like in
+/// `Sequence::to_source`.
+#[derive(Serialize, Deserialize)]
+struct SequenceData {
+    time_raster: TimeRaster,
+    name: Option<String>,
+    fov: Option<(f64, f64, f64)>,
+    definitions: BTreeMap<String, String>,
+    signature: Option<Signature>,
+    #[serde(default)]
+    unknown_sections: Vec<UnknownSection>,
+    blocks: Vec<BlockData>,
+    #[serde(default)]
+    rfs: BTreeMap<u32, RfData>,
+    #[serde(default)]
+    gradients: BTreeMap<u32, GradientData>,
+    #[serde(default)]
+    adcs: BTreeMap<u32, AdcData>,
+    #[serde(default)]
+    triggers: BTreeMap<u32, Trigger>,
+    #[serde(default)]
+    rotations: BTreeMap<u32, Rotation>,
+    #[serde(default)]
+    soft_delays: BTreeMap<u32, SoftDelay>,
+    #[serde(default)]
+    extensions: BTreeMap<u32, ExtensionData>,
+    #[serde(default)]
+    shapes: BTreeMap<u32, ShapeData>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct BlockData {
+    id: u32,
+    duration: f64,
+    rf: Option<u32>,
+    gx: Option<u32>,
+    gy: Option<u32>,
+    gz: Option<u32>,
+    adc: Option<u32>,
+    #[serde(default)]
+    labels: Vec<Label>,
+    #[serde(default)]
+    triggers: Vec<u32>,
+    rotation: Option<u32>,
+    soft_delay: Option<u32>,
+    #[serde(default)]
+    extensions: Vec<u32>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct RfData {
+    id: Option<u32>,
+    amp: f64,
+    phase: f64,
+    delay: f64,
+    freq: f64,
+    freq_ppm: f64,
+    phase_ppm: f64,
+    center: Option<f64>,
+    usage: RfUse,
+    amp_shape: u32,
+    phase_shape: u32,
+    shim_shape: Option<(u32, u32)>,
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "lowercase")]
+enum GradientData {
+    Free {
+        id: Option<u32>,
+        amp: f64,
+        first: Option<f64>,
+        last: Option<f64>,
+        delay: f64,
+        shape: u32,
+    },
+    Trap {
+        id: Option<u32>,
+        amp: f64,
+        rise: f64,
+        flat: f64,
+        fall: f64,
+        delay: f64,
+    },
+}
+
+#[derive(Serialize, Deserialize)]
+struct AdcData {
+    id: Option<u32>,
+    num: u32,
+    dwell: f64,
+    delay: f64,
+    freq: f64,
+    phase: f64,
+    freq_ppm: f64,
+    phase_ppm: f64,
+    phase_shape: Option<u32>,
+}
+
+/// `Extension::value` is produced by an `ExtensionHandler` and can't be
+/// serialized, it is `None` after deserialization.
+#[derive(Serialize, Deserialize)]
+struct ExtensionData {
+    name: String,
+    data: String,
+}
+
+#[derive(Serialize, Deserialize)]
+struct ShapeData {
+    id: Option<u32>,
+    time_id: Option<u32>,
+    samples: Vec<f64>,
+}
+
+impl Serialize for Sequence {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        SequenceData::new(self).serialize(serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for Sequence {
+    /// The sequence is not validated, so that sequences loaded in lenient
+    /// mode can be deserialized as well. References to missing table
+    /// entries are errors.
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        SequenceData::deserialize(deserializer)?
+            .into_sequence()
+            .map_err(D::Error::custom)
+    }
+}
+
+impl SequenceData {
+    fn new(seq: &Sequence) -> Self {
+        let blocks = &seq.blocks;
+        let rfs = IdTable::new(blocks.iter().filter_map(|block| block.rf.as_ref()));
+        let grads = IdTable::new(
+            (blocks.iter())
+                .flat_map(|block| [&block.gx, &block.gy, &block.gz])
+                .flatten(),
+        );
+        let adcs = IdTable::new(blocks.iter().filter_map(|block| block.adc.as_ref()));
+        let triggers = IdTable::new(blocks.iter().flat_map(|block| &block.triggers));
+        let rotations = IdTable::new(blocks.iter().filter_map(|block| block.rotation.as_ref()));
+        let soft_delays = IdTable::new(blocks.iter().filter_map(|block| block.soft_delay.as_ref()));
+        let extensions = IdTable::new(blocks.iter().flat_map(|block| &block.extensions));
+
+        let mut used_shapes = Vec::new();
+        for (_, rf) in rfs.sorted() {
+            used_shapes.extend([&rf.amp_shape, &rf.phase_shape]);
+            if let Some((mag, phase)) = &rf.shim_shape {
+                used_shapes.extend([mag, phase]);
+            }
+        }
+        for (_, grad) in grads.sorted() {
+            if let Gradient::Free { shape, .. } = grad.as_ref() {
+                used_shapes.push(shape);
+            }
+        }
+        for (_, adc) in adcs.sorted() {
+            used_shapes.extend(&adc.phase_shape);
+        }
+        let shapes = IdTable::new(used_shapes);
+
+        let blocks = (blocks.iter())
+            .map(|block| BlockData {
+                id: block.id,
+                duration: block.duration,
+                rf: block.rf.as_ref().map(|rf| rfs.get(rf)),
+                gx: block.gx.as_ref().map(|gx| grads.get(gx)),
+                gy: block.gy.as_ref().map(|gy| grads.get(gy)),
+                gz: block.gz.as_ref().map(|gz| grads.get(gz)),
+                adc: block.adc.as_ref().map(|adc| adcs.get(adc)),
+                labels: block.labels.clone(),
+                triggers: block.triggers.iter().map(|t| triggers.get(t)).collect(),
+                rotation: block.rotation.as_ref().map(|rot| rotations.get(rot)),
+                soft_delay: block.soft_delay.as_ref().map(|sd| soft_delays.get(sd)),
+                extensions: block
+                    .extensions
+                    .iter()
+                    .map(|ext| extensions.get(ext))
+                    .collect(),
+            })
+            .collect();
+
+        let rfs = rfs
+            .sorted()
+            .into_iter()
+            .map(|(id, rf)| {
+                let data = RfData {
+                    id: rf.id,
+                    amp: rf.amp,
+                    phase: rf.phase,
+                    delay: rf.delay,
+                    freq: rf.freq,
+                    freq_ppm: rf.freq_ppm,
+                    phase_ppm: rf.phase_ppm,
+                    center: rf.center,
+                    usage: rf.usage,
+                    amp_shape: shapes.get(&rf.amp_shape),
+                    phase_shape: shapes.get(&rf.phase_shape),
+                    shim_shape: (rf.shim_shape.as_ref())
+                        .map(|(mag, phase)| (shapes.get(mag), shapes.get(phase))),
+                };
+                (id, data)
+            })
+            .collect();
+
+        let gradients = grads
+            .sorted()
+            .into_iter()
+            .map(|(id, grad)| {
+                let data = match grad.as_ref() {
+                    Gradient::Free {
+                        id,
+                        amp,
+                        first,
+                        last,
+                        delay,
+                        shape,
+                    } => GradientData::Free {
+                        id: *id,
+                        amp: *amp,
+                        first: *first,
+                        last: *last,
+                        delay: *delay,
+                        shape: shapes.get(shape),
+                    },
+                    Gradient::Trap {
+                        id,
+                        amp,
+                        rise,
+                        flat,
+                        fall,
+                        delay,
+                    } => GradientData::Trap {
+                        id: *id,
+                        amp: *amp,
+                        rise: *rise,
+                        flat: *flat,
+                        fall: *fall,
+                        delay: *delay,
+                    },
+                };
+                (id, data)
+            })
+            .collect();
+
+        let adcs = adcs
+            .sorted()
+            .into_iter()
+            .map(|(id, adc)| {
+                let data = AdcData {
+                    id: adc.id,
+                    num: adc.num,
+                    dwell: adc.dwell,
+                    delay: adc.delay,
+                    freq: adc.freq,
+                    phase: adc.phase,
+                    freq_ppm: adc.freq_ppm,
+                    phase_ppm: adc.phase_ppm,
+                    phase_shape: adc.phase_shape.as_ref().map(|s| shapes.get(s)),
+                };
+                (id, data)
+            })
+            .collect();
+
+        let shapes = shapes
+            .sorted()
+            .into_iter()
+            .map(|(id, shape)| {
+                let data = ShapeData {
+                    id: shape.id,
+                    time_id: shape.time_id,
+                    samples: shape.samples.clone(),
+                };
+                (id, data)
+            })
+            .collect();
+
+        Self {
+            time_raster: seq.time_raster.clone(),
+            name: seq.name.clone(),
+            fov: seq.fov,
+            definitions: seq.definitions.clone().into_iter().collect(),
+            signature: seq.signature.clone(),
+            unknown_sections: seq.unknown_sections.clone(),
+            blocks,
+            rfs,
+            gradients,
+            adcs,
+            triggers: cloned(&triggers),
+            rotations: cloned(&rotations),
+            soft_delays: cloned(&soft_delays),
+            extensions: (extensions.sorted().into_iter())
+                .map(|(id, ext)| {
+                    let data = ExtensionData {
+                        name: ext.name.clone(),
+                        data: ext.data.clone(),
+                    };
+                    (id, data)
+                })
+                .collect(),
+            shapes,
+        }
+    }
+
+    fn into_sequence(self) -> Result<Sequence, String> {
+        let shapes: HashMap<_, _> = (self.shapes.into_iter())
+            .map(|(id, shape)| {
+                let shape = Shape {
+                    samples: shape.samples,
+                    id: shape.id,
+                    time_id: shape.time_id,
+                };
+                (id, Arc::new(shape))
+            })
+            .collect();
+        let shape = |id: u32| lookup(&shapes, id, "shape");
+
+        let rfs = (self.rfs.into_iter())
+            .map(|(id, rf)| {
+                let rf = Rf {
+                    id: rf.id,
+                    amp: rf.amp,
+                    phase: rf.phase,
+                    delay: rf.delay,
+                    freq: rf.freq,
+                    freq_ppm: rf.freq_ppm,
+                    phase_ppm: rf.phase_ppm,
+                    center: rf.center,
+                    usage: rf.usage,
+                    amp_shape: shape(rf.amp_shape)?,
+                    phase_shape: shape(rf.phase_shape)?,
+                    shim_shape: match rf.shim_shape {
+                        Some((mag, phase)) => Some((shape(mag)?, shape(phase)?)),
+                        None => None,
+                    },
+                };
+                Ok((id, Arc::new(rf)))
+            })
+            .collect::<Result<HashMap<_, _>, String>>()?;
+
+        let grads = (self.gradients.into_iter())
+            .map(|(key, grad)| {
+                let grad = match grad {
+                    GradientData::Free {
+                        id,
+                        amp,
+                        first,
+                        last,
+                        delay,
+                        shape: shape_id,
+                    } => Gradient::Free {
+                        id,
+                        amp,
+                        first,
+                        last,
+                        delay,
+                        shape: shape(shape_id)?,
+                    },
+                    GradientData::Trap {
+                        id,
+                        amp,
+                        rise,
+                        flat,
+                        fall,
+                        delay,
+                    } => Gradient::Trap {
+                        id,
+                        amp,
+                        rise,
+                        flat,
+                        fall,
+                        delay,
+                    },
+                };
+                Ok((key, Arc::new(grad)))
+            })
+            .collect::<Result<HashMap<_, _>, String>>()?;
+
+        let adcs = (self.adcs.into_iter())
+            .map(|(id, adc)| {
+                let adc = Adc {
+                    id: adc.id,
+                    num: adc.num,
+                    dwell: adc.dwell,
+                    delay: adc.delay,
+                    freq: adc.freq,
+                    phase: adc.phase,
+                    freq_ppm: adc.freq_ppm,
+                    phase_ppm: adc.phase_ppm,
+                    phase_shape: adc.phase_shape.map(shape).transpose()?,
+                };
+                Ok((id, Arc::new(adc)))
+            })
+            .collect::<Result<HashMap<_, _>, String>>()?;
+
+        let triggers = shared(self.triggers);
+        let rotations = shared(self.rotations);
+        let soft_delays = shared(self.soft_delays);
+        let extensions: HashMap<_, _> = (self.extensions.into_iter())
+            .map(|(id, ext)| {
+                let ext = Extension {
+                    name: ext.name,
+                    data: ext.data,
+                    value: None,
+                };
+                (id, Arc::new(ext))
+            })
+            .collect();
+
+        let blocks = (self.blocks.into_iter())
+            .map(|block| {
+                Ok(Block {
+                    id: block.id,
+                    duration: block.duration,
+                    rf: (block.rf.map(|id| lookup(&rfs, id, "rf"))).transpose()?,
+                    gx: (block.gx.map(|id| lookup(&grads, id, "gradient"))).transpose()?,
+                    gy: (block.gy.map(|id| lookup(&grads, id, "gradient"))).transpose()?,
+                    gz: (block.gz.map(|id| lookup(&grads, id, "gradient"))).transpose()?,
+                    adc: (block.adc.map(|id| lookup(&adcs, id, "adc"))).transpose()?,
+                    labels: block.labels,
+                    triggers: (block.triggers.into_iter())
+                        .map(|id| lookup(&triggers, id, "trigger"))
+                        .collect::<Result<_, _>>()?,
+                    rotation: (block.rotation.map(|id| lookup(&rotations, id, "rotation")))
+                        .transpose()?,
+                    soft_delay: (block.soft_delay)
+                        .map(|id| lookup(&soft_delays, id, "soft delay"))
+                        .transpose()?,
+                    extensions: (block.extensions.into_iter())
+                        .map(|id| lookup(&extensions, id, "extension"))
+                        .collect::<Result<_, _>>()?,
+                })
+            })
+            .collect::<Result<_, String>>()?;
+
+        Ok(Sequence {
+            time_raster: self.time_raster,
+            name: self.name,
+            fov: self.fov,
+            definitions: self.definitions.into_iter().collect(),
+            blocks,
+            signature: self.signature,
+            unknown_sections: self.unknown_sections,
+        })
+    }
+}
+
+// Events without an ID in the .seq file are numbered from 1
+
+impl SourceId for Trigger {
+    fn source_id(&self) -> Option<u32> {
+        None
+    }
+}
+
+impl SourceId for Rotation {
+    fn source_id(&self) -> Option<u32> {
+        None
+    }
+}
+
+impl SourceId for SoftDelay {
+    fn source_id(&self) -> Option<u32> {
+        None
+    }
+}
+
+impl SourceId for Extension {
+    fn source_id(&self) -> Option<u32> {
+        None
+    }
+}
+
+impl SourceId for Shape {
+    fn source_id(&self) -> Option<u32> {
+        self.id
+    }
+}
+
+fn cloned<T: Clone + SourceId>(table: &IdTable<T>) -> BTreeMap<u32, T> {
+    (table.sorted().into_iter())
+        .map(|(id, item)| (id, item.as_ref().clone()))
+        .collect()
+}
+
+fn shared<T>(table: BTreeMap<u32, T>) -> HashMap<u32, Arc<T>> {
+    (table.into_iter())
+        .map(|(id, item)| (id, Arc::new(item)))
+        .collect()
+}
+
+fn lookup<T>(table: &HashMap<u32, Arc<T>>, id: u32, what: &str) -> Result<Arc<T>, String> {
+    table
+        .get(&id)
+        .cloned()
+        .ok_or_else(|| format!("Reference to missing {what} with ID {id}"))
+}
@@ -0,0 +1,50 @@
+// Verification and creation of the [SIGNATURE] section
+use crate::error::SignatureError;
+
+/// The signature of a .seq file, as stored in its [SIGNATURE] section.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Signature {
+    /// Hash algorithm, pulseq currently only uses `md5`
+    pub typ: String,
+    /// Hash as stored in the file
+    pub hash: String,
+    /// Hash of the file content, if the algorithm is supported and the
+    /// sequence was loaded from source
+    pub computed: Option<String>,
+}
+
+impl Signature {
+    /// Returns `None` if the signature could not be checked
+    pub fn is_valid(&self) -> Option<bool> {
+        self.computed
+            .as_ref()
+            .map(|computed| computed.eq_ignore_ascii_case(&self.hash))
+    }
+
+    /// Computes the hash of the source, which covers everything up to the
+    /// newline in front of the [SIGNATURE] section (as done by pypulseq).
+    pub(crate) fn verify(&mut self, source: &str) {
+        let content = source
+            .rfind("\n[SIGNATURE]")
+            .map_or(source, |end| &source[..end]);
+        if self.typ.eq_ignore_ascii_case("md5") {
+            self.computed = Some(md5_hex(content));
+        }
+    }
+
+    pub(crate) fn check(&self) -> Result<(), SignatureError> {
+        match self.is_valid() {
+            Some(true) => Ok(()),
+            Some(false) => Err(SignatureError::Mismatch {
+                expected: self.hash.clone(),
+                computed: self.computed.clone().unwrap_or_default(),
+            }),
+            None => Err(SignatureError::UnsupportedType(self.typ.clone())),
+        }
+    }
+}
+
+pub(crate) fn md5_hex(content: &str) -> String {
+    format!("{:x}", md5::compute(content))
+}
@@ -0,0 +1,41 @@
+// Typed representation of the DELAYS (soft delay) extension (pulseq 1.5)
+use crate::error::ConversionError;
+
+/// A soft delay makes the duration of its block adjustable at the scanner.
+/// The interpreter sets the block duration to `offset + value / factor`,
+/// where `value` is the user input for the soft delay with the ID `num`.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct SoftDelay {
+    /// ID of the user-adjustable parameter, shared by all blocks using it
+    pub num: u32,
+    /// Unit: `[s]`
+    pub offset: f64,
+    pub factor: f64,
+    /// Name shown on the scanner, e.g. `TE` or `TR`
+    pub hint: String,
+}
+
+impl SoftDelay {
+    /// Parses the data of a DELAYS extension object:
+    /// `num offset factor hint`, with the offset given in `[us]`
+    pub(crate) fn parse(data: &str) -> Result<Self, ConversionError> {
+        let err = || ConversionError::InvalidExtensionData {
+            name: "DELAYS".to_owned(),
+            data: data.to_owned(),
+        };
+
+        let splits: Vec<_> = data.split_whitespace().collect();
+        let [num, offset, factor, hint] = splits[..] else {
+            return Err(err());
+        };
+        let offset: f64 = offset.parse().map_err(|_| err())?;
+
+        Ok(Self {
+            num: num.parse().map_err(|_| err())?,
+            offset: offset * 1e-6,
+            factor: factor.parse().map_err(|_| err())?,
+            hint: hint.to_owned(),
+        })
+    }
+}
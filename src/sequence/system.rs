@@ -0,0 +1,149 @@
+// Hardware limits of a scanner and checking sequences against them
+use super::*;
+
+/// Gyromagnetic ratio of hydrogen, as used by pypulseq. Unit: `[Hz/T]`
+pub const GAMMA: f64 = 42.576e6;
+
+/// Hardware limits of a scanner, like pypulseq's `Opts`. Gradient limits are
+/// given in the units used by pulseq: multiply T/m by `GAMMA` to get Hz/m.
+#[derive(Debug, Clone, PartialEq)]
+pub struct System {
+    /// Unit: `[Hz/m]`
+    pub max_grad: f64,
+    /// Unit: `[Hz/m/s]`
+    pub max_slew: f64,
+    /// Time needed after an RF pulse before the block may end. Unit: `[s]`
+    pub rf_ringdown_time: f64,
+    /// Minimal delay of RF pulses. Unit: `[s]`
+    pub rf_dead_time: f64,
+    /// Minimal delay of ADCs and time needed after them. Unit: `[s]`
+    pub adc_dead_time: f64,
+    /// Main field strength, unit: `[T]`
+    pub b0: f64,
+}
+
+impl Default for System {
+    /// The defaults of pypulseq: 40 mT/m, 170 T/m/s, no dead times, 1.5 T
+    fn default() -> Self {
+        Self {
+            max_grad: 40e-3 * GAMMA,
+            max_slew: 170.0 * GAMMA,
+            rf_ringdown_time: 0.0,
+            rf_dead_time: 0.0,
+            adc_dead_time: 0.0,
+            b0: 1.5,
+        }
+    }
+}
+
+// Allow for rounding errors of values that are exactly at the limit
+pub(super) const TOLERANCE: f64 = 1e-6;
+
+impl Sequence {
+    /// Checks all blocks against the hardware limits and returns every
+    /// violation found. Gradients are checked per channel, as stored in the
+    /// file. Slew rates of free gradients are calculated between samples.
+    pub fn check_system(&self, system: &System) -> Vec<ValidationError> {
+        let mut errors = Vec::new();
+
+        for block in &self.blocks {
+            let block_id = block.id;
+
+            for (grad, ty) in [
+                (&block.gx, EventType::Gx),
+                (&block.gy, EventType::Gy),
+                (&block.gz, EventType::Gz),
+            ] {
+                if let Some(grad) = grad {
+                    check_gradient(
+                        grad,
+                        ty,
+                        block_id,
+                        self.time_raster.grad,
+                        system,
+                        &mut errors,
+                    );
+                }
+            }
+
+            if let Some(rf) = &block.rf {
+                if rf.delay < system.rf_dead_time * (1.0 - TOLERANCE) {
+                    errors.push(ValidationError::RfDeadTime {
+                        block_id,
+                        delay: rf.delay,
+                        dead_time: system.rf_dead_time,
+                    });
+                }
+                let remaining = block.duration - rf.duration(self.time_raster.rf);
+                if remaining < system.rf_ringdown_time * (1.0 - TOLERANCE) {
+                    errors.push(ValidationError::RfRingdownTime {
+                        block_id,
+                        remaining,
+                        ringdown_time: system.rf_ringdown_time,
+                    });
+                }
+            }
+
+            if let Some(adc) = &block.adc {
+                if adc.delay < system.adc_dead_time * (1.0 - TOLERANCE) {
+                    errors.push(ValidationError::AdcDeadTime {
+                        block_id,
+                        delay: adc.delay,
+                        dead_time: system.adc_dead_time,
+                    });
+                }
+                let remaining = block.duration - adc.duration();
+                if remaining < system.adc_dead_time * (1.0 - TOLERANCE) {
+                    errors.push(ValidationError::AdcDeadTimeAfter {
+                        block_id,
+                        remaining,
+                        dead_time: system.adc_dead_time,
+                    });
+                }
+            }
+        }
+
+        errors
+    }
+}
+
+fn check_gradient(
+    grad: &Gradient,
+    ty: EventType,
+    block_id: u32,
+    grad_raster: f64,
+    system: &System,
+    errors: &mut Vec<ValidationError>,
+) {
+    let points = grad.breakpoints(grad_raster);
+
+    let amp = max_abs(points.iter().map(|p| p.1));
+    if amp.abs() > system.max_grad * (1.0 + TOLERANCE) {
+        errors.push(ValidationError::GradientAmplitude {
+            ty,
+            block_id,
+            amp,
+            max: system.max_grad,
+        });
+    }
+
+    // Trap ramps can have a duration of zero, which is an infinite slew rate
+    let slew = points
+        .windows(2)
+        .filter(|w| w[0].1 != w[1].1)
+        .map(|w| (w[1].1 - w[0].1) / (w[1].0 - w[0].0));
+    let slew = max_abs(slew);
+    if slew.abs() > system.max_slew * (1.0 + TOLERANCE) {
+        errors.push(ValidationError::SlewRate {
+            ty,
+            block_id,
+            slew,
+            max: system.max_slew,
+        });
+    }
+}
+
+/// Returns the value with the largest magnitude, keeping its sign
+fn max_abs(values: impl Iterator<Item = f64>) -> f64 {
+    values.fold(0.0, |a, b| if b.abs() > a.abs() { b } else { a })
+}
@@ -0,0 +1,148 @@
+// Absolute timing of blocks and events
+use super::*;
+
+/// Absolute start times of all blocks. Times are stored as integer multiples
+/// of the block raster, so they don't accumulate rounding errors over long
+/// sequences. Computed by `Sequence::timeline`, it is not updated if blocks
+/// are changed afterwards.
+pub struct Timeline {
+    block_raster: f64,
+    /// Start of every block followed by the end of the sequence,
+    /// in units of the block raster
+    ticks: Vec<u64>,
+}
+
+impl Timeline {
+    /// Block durations are rounded up to the block raster. Since pulseq 1.4,
+    /// they are stored as integer multiples of it, older files should be
+    /// checked with the raster validation.
+    pub fn new(blocks: &[Block], block_raster: f64) -> Self {
+        let mut tick = 0;
+        let mut ticks = Vec::with_capacity(blocks.len() + 1);
+        ticks.push(tick);
+        for block in blocks {
+            // Tolerance for durations that are not exactly representable
+            tick += (block.duration / block_raster - 1e-6).ceil().max(0.0) as u64;
+            ticks.push(tick);
+        }
+
+        Self {
+            block_raster,
+            ticks,
+        }
+    }
+
+    pub fn block_count(&self) -> usize {
+        self.ticks.len() - 1
+    }
+
+    /// Unit: `[s]`
+    pub fn duration(&self) -> f64 {
+        self.time(*self.ticks.last().unwrap())
+    }
+
+    /// Unit: `[s]`. Panics if the index is out of bounds.
+    pub fn block_start(&self, index: usize) -> f64 {
+        assert!(index < self.block_count());
+        self.time(self.ticks[index])
+    }
+
+    /// Unit: `[s]`. Panics if the index is out of bounds.
+    pub fn block_end(&self, index: usize) -> f64 {
+        assert!(index < self.block_count());
+        self.time(self.ticks[index + 1])
+    }
+
+    /// Index of the block that is played at the time `t` (in `[s]`),
+    /// blocks are half open intervals `[start, end)`.
+    /// Returns `None` if `t` is outside of the sequence.
+    pub fn block_at(&self, t: f64) -> Option<usize> {
+        let index = self.ticks.partition_point(|&tick| self.time(tick) <= t);
+        // Index 0: before the first block, last index: after the sequence
+        index
+            .checked_sub(1)
+            .filter(|&index| index < self.block_count())
+    }
+
+    fn time(&self, tick: u64) -> f64 {
+        tick as f64 * self.block_raster
+    }
+}
+
+/// Reference to an event of a block
+#[derive(Clone, Copy)]
+pub enum Event<'a> {
+    Rf(&'a Rf),
+    Gx(&'a Gradient),
+    Gy(&'a Gradient),
+    Gz(&'a Gradient),
+    Adc(&'a Adc),
+    Trigger(&'a Trigger),
+}
+
+/// An event with its absolute timing. The start is the end of the event
+/// delay, when the event actually begins to play out.
+#[derive(Clone, Copy)]
+pub struct TimedEvent<'a> {
+    /// Index into `Sequence::blocks`
+    pub block_index: usize,
+    pub event: Event<'a>,
+    /// Unit: `[s]`
+    pub start: f64,
+    /// Unit: `[s]`
+    pub end: f64,
+}
+
+impl Sequence {
+    pub fn timeline(&self) -> Timeline {
+        Timeline::new(&self.blocks, self.time_raster.block)
+    }
+
+    /// Iterates over all events, ordered by block and, within a block, in the
+    /// order RF, GX, GY, GZ, ADC, triggers.
+    pub fn events(&self) -> impl Iterator<Item = TimedEvent<'_>> {
+        let timeline = self.timeline();
+        let raster = &self.time_raster;
+
+        self.blocks
+            .iter()
+            .enumerate()
+            .flat_map(move |(index, block)| {
+                let block_start = timeline.block_start(index);
+
+                // (event, delay, duration including delay)
+                let mut events = Vec::new();
+                if let Some(rf) = &block.rf {
+                    events.push((Event::Rf(rf), rf.delay, rf.duration(raster.rf)));
+                }
+                for (grad, event) in [
+                    (&block.gx, Event::Gx as fn(_) -> _),
+                    (&block.gy, Event::Gy),
+                    (&block.gz, Event::Gz),
+                ] {
+                    if let Some(grad) = grad {
+                        events.push((event(grad), grad.delay(), grad.duration(raster.grad)));
+                    }
+                }
+                if let Some(adc) = &block.adc {
+                    events.push((Event::Adc(adc), adc.delay, adc.duration()));
+                }
+                for trigger in &block.triggers {
+                    events.push((
+                        Event::Trigger(trigger),
+                        trigger.delay,
+                        trigger.total_duration(),
+                    ));
+                }
+
+                events
+                    .into_iter()
+                    .map(move |(event, delay, duration)| TimedEvent {
+                        block_index: index,
+                        event,
+                        start: block_start + delay,
+                        end: block_start + duration,
+                    })
+            })
+    }
+}
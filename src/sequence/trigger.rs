@@ -0,0 +1,76 @@
+// Typed representation of the TRIGGERS extension
+use super::*;
+use crate::error::ConversionError;
+
+/// A trigger, as created by pypulseq's `make_trigger` (physio input) or
+/// `make_digital_output_pulse` (output trigger).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum TriggerType {
+    /// Wait for a physiological trigger signal
+    Physio,
+    /// Play out a digital output pulse
+    Output,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Trigger {
+    pub ty: TriggerType,
+    /// Physio: 1 = `physio1`, 2 = `physio2`.
+    /// Output: 1 = `osc0`, 2 = `osc1`, 3 = `ext1`
+    pub channel: u32,
+    /// Unit: `[s]`
+    pub delay: f64,
+    /// Unit: `[s]`
+    pub duration: f64,
+}
+
+impl Trigger {
+    /// Parses the data of a TRIGGERS extension object:
+    /// `type channel delay duration`, with delay and duration given in `[us]`
+    pub(crate) fn parse(data: &str) -> Result<Self, ConversionError> {
+        let err = || ConversionError::InvalidExtensionData {
+            name: "TRIGGERS".to_owned(),
+            data: data.to_owned(),
+        };
+
+        let splits: Vec<_> = data.split_whitespace().collect();
+        let [ty, channel, delay, duration] = splits[..] else {
+            return Err(err());
+        };
+        let ty = match ty {
+            "1" => TriggerType::Output,
+            "2" => TriggerType::Physio,
+            _ => return Err(err()),
+        };
+        let channel = channel.parse().map_err(|_| err())?;
+        let delay: f64 = delay.parse().map_err(|_| err())?;
+        let duration: f64 = duration.parse().map_err(|_| err())?;
+
+        Ok(Self {
+            ty,
+            channel,
+            delay: delay * 1e-6,
+            duration: duration * 1e-6,
+        })
+    }
+
+    /// Time from the start of the block to the end of the trigger
+    pub fn total_duration(&self) -> f64 {
+        self.delay + self.duration
+    }
+
+    pub(crate) fn validate(&self, block_id: u32) -> Result<(), ValidationError> {
+        for timing in [self.delay, self.duration] {
+            if timing < 0.0 {
+                return Err(ValidationError::NegativeTiming {
+                    ty: EventType::Trigger,
+                    block_id,
+                    timing,
+                });
+            }
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,602 @@
+// Serialization of the sequence into a pulseq 1.4 .seq file
+use std::{
+    collections::{HashMap, HashSet},
+    fmt::Write,
+};
+
+use super::*;
+
+impl Sequence {
+    /// Serialize the sequence into the pulseq 1.4 file format.
+    /// Events that are shared between blocks (same `Arc`) are written once.
+    /// Events and shapes keep the IDs of the file they were loaded from,
+    /// unless they collide; new events get the lowest IDs not kept by others.
+    /// Unknown sections are written back unchanged.
+    /// Information that can't be represented in pulseq 1.4 is not written:
+    /// RF center, use and ppm offsets, ADC ppm offsets and phase shapes and
+    /// the first / last amplitudes of free gradients, unless they are
+    /// piecewise linear with corners on the raster and written with a time
+    /// shape.
+    /// The file is signed with an MD5 hash like pypulseq does.
+    pub fn to_source(&self) -> String {
+        let mut out = String::new();
+        // Writing into a String can't fail
+        self.write_seq(&mut out).unwrap();
+
+        let hash = signature::md5_hex(&out);
+        out += "\n[SIGNATURE]\n";
+        out += "# This is the hash of the Pulseq file, calculated right before the [SIGNATURE] section was added\n";
+        out += "# It can be reproduced/verified with md5sum if the file trimmed to the position right above [SIGNATURE]\n";
+        out += "# The new line character preceding [SIGNATURE] BELONGS to the signature (and needs to be stripped away for recalculating/verification)\n";
+        out += "Type md5\n";
+        out += &format!("Hash {hash}\n");
+        out
+    }
+
+    pub fn to_file<P: AsRef<Path>>(&self, path: P) -> Result<(), std::io::Error> {
+        std::fs::write(path, self.to_source())
+    }
+
+    fn write_seq(&self, out: &mut String) -> std::fmt::Result {
+        let blocks = &self.blocks;
+        let rfs = IdTable::new(blocks.iter().filter_map(|block| block.rf.as_ref()));
+        let grads = IdTable::new(
+            (blocks.iter())
+                .flat_map(|block| [&block.gx, &block.gy, &block.gz])
+                .flatten(),
+        );
+        let adcs = IdTable::new(blocks.iter().filter_map(|block| block.adc.as_ref()));
+        let mut exts = ExtensionTable::default();
+
+        // Shapes in the order they are written
+        let mut used_shapes = Vec::new();
+        for (_, rf) in rfs.sorted() {
+            used_shapes.extend([&rf.amp_shape, &rf.phase_shape]);
+            if let Some((mag, phase)) = &rf.shim_shape {
+                used_shapes.extend([mag, phase]);
+            }
+        }
+        let corners: HashMap<usize, (Arc<Shape>, Arc<Shape>)> = (grads.items.iter())
+            .filter_map(|(_, grad)| Some((Arc::as_ptr(grad) as usize, corner_shapes(grad)?)))
+            .collect();
+        for (_, grad) in grads.sorted() {
+            if let Some((amp_shape, time_shape)) = corners.get(&(Arc::as_ptr(grad) as usize)) {
+                used_shapes.extend([amp_shape, time_shape]);
+            } else if let Gradient::Free { shape, .. } = grad.as_ref() {
+                used_shapes.push(shape);
+            }
+        }
+        let shapes = ShapeTable::new(used_shapes);
+
+        writeln!(out, "# Pulseq sequence file")?;
+        writeln!(out, "# Created by pulseq-rs")?;
+
+        writeln!(out, "\n[VERSION]")?;
+        writeln!(out, "major 1")?;
+        writeln!(out, "minor 4")?;
+        writeln!(out, "revision 1")?;
+
+        writeln!(out, "\n[DEFINITIONS]")?;
+        writeln!(out, "AdcRasterTime {}", self.time_raster.adc)?;
+        writeln!(out, "BlockDurationRaster {}", self.time_raster.block)?;
+        writeln!(out, "GradientRasterTime {}", self.time_raster.grad)?;
+        writeln!(out, "RadiofrequencyRasterTime {}", self.time_raster.rf)?;
+        if let Some(name) = &self.name {
+            writeln!(out, "Name {name}")?;
+        }
+        if let Some((x, y, z)) = self.fov {
+            writeln!(out, "FOV {x} {y} {z}")?;
+        }
+        // Pre 1.4 sequences keep all definitions in the map, skip duplicates
+        let written = |key: &str| match key {
+            "AdcRasterTime" | "BlockDurationRaster" => true,
+            "GradientRasterTime" | "RadiofrequencyRasterTime" => true,
+            "Name" => self.name.is_some(),
+            "FOV" => self.fov.is_some(),
+            _ => false,
+        };
+        let mut defs: Vec<_> = self
+            .definitions
+            .iter()
+            .filter(|(key, _)| !written(key))
+            .collect();
+        defs.sort();
+        for (key, value) in defs {
+            writeln!(out, "{key} {value}")?;
+        }
+
+        writeln!(out, "\n# Format of blocks:")?;
+        writeln!(out, "# NUM DUR RF  GX  GY  GZ  ADC  EXT")?;
+        writeln!(out, "[BLOCKS]")?;
+        for block in &self.blocks {
+            let dur = (block.duration / self.time_raster.block).round() as u64;
+            let ext = exts.insert_block(block);
+            writeln!(
+                out,
+                "{} {dur} {} {} {} {} {} {ext}",
+                block.id,
+                rfs.get_opt(&block.rf),
+                grads.get_opt(&block.gx),
+                grads.get_opt(&block.gy),
+                grads.get_opt(&block.gz),
+                adcs.get_opt(&block.adc),
+            )?;
+        }
+
+        if !rfs.items.is_empty() {
+            writeln!(out, "\n# Format of RF events:")?;
+            writeln!(
+                out,
+                "# id amplitude mag_id phase_id time_shape_id delay freq phase"
+            )?;
+            writeln!(
+                out,
+                "# ..        Hz   ....     ....          ....    us   Hz   rad"
+            )?;
+            writeln!(out, "[RF]")?;
+            for (id, rf) in rfs.sorted() {
+                write!(
+                    out,
+                    "{id} {} {} {} 0 {} {} {}",
+                    rf.amp,
+                    shapes.get(&rf.amp_shape),
+                    shapes.get(&rf.phase_shape),
+                    us(rf.delay),
+                    rf.freq,
+                    rf.phase,
+                )?;
+                if let Some((mag, phase)) = &rf.shim_shape {
+                    write!(out, " {} {}", shapes.get(mag), shapes.get(phase))?;
+                }
+                writeln!(out)?;
+            }
+        }
+
+        let is_free = |grad: &Arc<Gradient>| matches!(grad.as_ref(), Gradient::Free { .. });
+        if grads.items.iter().any(|(_, g)| is_free(g)) {
+            writeln!(out, "\n# Format of arbitrary gradients:")?;
+            writeln!(out, "#   time_shape_id of 0 means default timing (stepping with grad_raster starting at 1/2 of grad_raster)")?;
+            writeln!(out, "# id amplitude amp_shape_id time_shape_id delay")?;
+            writeln!(out, "# ..      Hz/m       ..         ..          us")?;
+            writeln!(out, "[GRADIENTS]")?;
+            for (id, grad) in grads.sorted() {
+                if let Gradient::Free {
+                    amp, delay, shape, ..
+                } = grad.as_ref()
+                {
+                    let (shape_id, time_id) = match corners.get(&(Arc::as_ptr(grad) as usize)) {
+                        Some((amp_shape, time_shape)) => {
+                            (shapes.get(amp_shape), shapes.get(time_shape))
+                        }
+                        None => (shapes.get(shape), 0),
+                    };
+                    writeln!(out, "{id} {amp} {shape_id} {time_id} {}", us(*delay))?;
+                }
+            }
+        }
+
+        if grads.items.iter().any(|(_, g)| !is_free(g)) {
+            writeln!(out, "\n# Format of trapezoid gradients:")?;
+            writeln!(out, "# id amplitude rise flat fall delay")?;
+            writeln!(out, "# ..      Hz/m   us   us   us    us")?;
+            writeln!(out, "[TRAP]")?;
+            for (id, grad) in grads.sorted() {
+                if let Gradient::Trap {
+                    amp,
+                    rise,
+                    flat,
+                    fall,
+                    delay,
+                    ..
+                } = grad.as_ref()
+                {
+                    writeln!(
+                        out,
+                        "{id} {amp} {} {} {} {}",
+                        us(*rise),
+                        us(*flat),
+                        us(*fall),
+                        us(*delay)
+                    )?;
+                }
+            }
+        }
+
+        if !adcs.items.is_empty() {
+            writeln!(out, "\n# Format of ADC events:")?;
+            writeln!(out, "# id num dwell delay freq phase")?;
+            writeln!(out, "# ..  ..    ns    us   Hz   rad")?;
+            writeln!(out, "[ADC]")?;
+            for (id, adc) in adcs.sorted() {
+                writeln!(
+                    out,
+                    "{id} {} {} {} {} {}",
+                    adc.num,
+                    clean(adc.dwell * 1e9),
+                    us(adc.delay),
+                    adc.freq,
+                    adc.phase,
+                )?;
+            }
+        }
+
+        if !exts.refs.is_empty() {
+            writeln!(out, "\n# Format of extension lists:")?;
+            writeln!(out, "# id type ref next_id")?;
+            writeln!(out, "# next_id of 0 terminates the list")?;
+            writeln!(
+                out,
+                "# Extension list is followed by extension specifications"
+            )?;
+            writeln!(out, "[EXTENSIONS]")?;
+            for (id, (spec_id, obj_id, next)) in exts.refs.iter().enumerate() {
+                writeln!(out, "{} {spec_id} {obj_id} {next}", id + 1)?;
+            }
+            for (spec_id, spec) in exts.specs.iter().enumerate() {
+                writeln!(out, "\nextension {} {}", spec.name, spec_id + 1)?;
+                for (obj_id, data) in spec.objects.iter().enumerate() {
+                    writeln!(out, "{} {data}", obj_id + 1)?;
+                }
+            }
+        }
+
+        if !shapes.items.is_empty() {
+            writeln!(out, "\n# Sequence Shapes")?;
+            writeln!(out, "[SHAPES]")?;
+            for (id, shape) in shapes.sorted() {
+                writeln!(out, "\nshape_id {id}")?;
+                writeln!(out, "num_samples {}", shape.samples.len())?;
+                for sample in compress_shape(&shape.samples) {
+                    writeln!(out, "{sample}")?;
+                }
+            }
+        }
+
+        for section in &self.unknown_sections {
+            writeln!(out, "\n[{}]", section.name)?;
+            for line in &section.lines {
+                writeln!(out, "{line}")?;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// Amplitude and time shape of free gradients with known first and last
+/// amplitude that are piecewise linear with corners on the raster, like
+/// extended trapezoids. Written this way, the first and last amplitude
+/// survive in pulseq 1.4, where they are the first and last corner.
+fn corner_shapes(grad: &Gradient) -> Option<(Arc<Shape>, Arc<Shape>)> {
+    let Gradient::Free {
+        amp,
+        first: Some(first),
+        last: Some(last),
+        shape,
+        ..
+    } = grad
+    else {
+        return None;
+    };
+    if *amp == 0.0 || shape.samples.is_empty() {
+        return None;
+    }
+
+    // Samples are in the middle between the values on the raster edges
+    let mut edges = vec![first / amp];
+    for sample in &shape.samples {
+        edges.push(2.0 * sample - edges[edges.len() - 1]);
+    }
+    let n = shape.samples.len();
+    if (edges[n] - last / amp).abs() > 1e-9 {
+        return None;
+    }
+
+    // Only keep the corners. Their values are rounded to remove the noise
+    // of the reconstruction, so that writing a loaded file again is stable.
+    let corner =
+        |i: usize| i == 0 || i == n || (edges[i - 1] - 2.0 * edges[i] + edges[i + 1]).abs() > 1e-9;
+    let (times, values): (Vec<f64>, Vec<f64>) = (0..=n)
+        .filter(|&i| corner(i))
+        .map(|i| (i as f64, (edges[i] * 1e12).round() / 1e12))
+        .unzip();
+
+    // Loaded time shaped gradients keep their shape IDs
+    let amp_shape = Shape {
+        samples: values,
+        id: shape.time_id.and(shape.id),
+        time_id: None,
+    };
+    let time_shape = Shape {
+        samples: times,
+        id: shape.time_id,
+        time_id: None,
+    };
+    Some((Arc::new(amp_shape), Arc::new(time_shape)))
+}
+
+/// Converts a time to integer microseconds
+fn us(t: f64) -> i64 {
+    (t * 1e6).round() as i64
+}
+
+/// Removes floating point noise from values that were converted between units
+fn clean(x: f64) -> f64 {
+    format!("{x:.12e}").parse().unwrap()
+}
+
+/// Events that know the ID they had in the file they were loaded from
+pub(super) trait SourceId {
+    fn source_id(&self) -> Option<u32>;
+}
+
+impl SourceId for Rf {
+    fn source_id(&self) -> Option<u32> {
+        self.id
+    }
+}
+
+impl SourceId for Gradient {
+    fn source_id(&self) -> Option<u32> {
+        self.id()
+    }
+}
+
+impl SourceId for Adc {
+    fn source_id(&self) -> Option<u32> {
+        self.id
+    }
+}
+
+/// Assigns IDs to items with the given original IDs: every item keeps its
+/// original ID unless an earlier item has it already, the others are
+/// numbered from 1 on. All original IDs are reserved before new ones are
+/// handed out, so they can't be taken by items that appear earlier.
+fn assign_ids(source_ids: &[Option<u32>]) -> Vec<u32> {
+    let mut used = HashSet::new();
+    let kept: Vec<Option<u32>> = (source_ids.iter())
+        .map(|id| id.filter(|&id| id != 0 && used.insert(id)))
+        .collect();
+
+    let mut next_free = 1;
+    (kept.into_iter())
+        .map(|id| {
+            id.unwrap_or_else(|| {
+                while used.contains(&next_free) {
+                    next_free += 1;
+                }
+                used.insert(next_free);
+                next_free
+            })
+        })
+        .collect()
+}
+
+/// Assigns IDs to events, keeping their original ID where possible.
+/// Events are identified by their `Arc`, not by their content.
+pub(super) struct IdTable<T> {
+    ids: HashMap<usize, u32>,
+    /// Events with their ID, in order of first use
+    pub items: Vec<(u32, Arc<T>)>,
+}
+
+impl<T: SourceId> IdTable<T> {
+    /// Collects all events, which may contain duplicates
+    pub fn new<'a>(events: impl IntoIterator<Item = &'a Arc<T>>) -> Self
+    where
+        T: 'a,
+    {
+        let mut seen = HashSet::new();
+        let events: Vec<_> = (events.into_iter())
+            .filter(|event| seen.insert(Arc::as_ptr(event)))
+            .collect();
+        let source_ids: Vec<_> = events.iter().map(|event| event.source_id()).collect();
+
+        let items: Vec<_> = (assign_ids(&source_ids).into_iter())
+            .zip(events)
+            .map(|(id, event)| (id, event.clone()))
+            .collect();
+        let ids = (items.iter())
+            .map(|(id, event)| (Arc::as_ptr(event) as usize, *id))
+            .collect();
+        Self { ids, items }
+    }
+
+    /// ID of an event that was passed to `new`
+    pub fn get(&self, item: &Arc<T>) -> u32 {
+        self.ids[&(Arc::as_ptr(item) as usize)]
+    }
+
+    /// Returns 0 for `None`, which is how empty slots are written in pulseq
+    pub fn get_opt(&self, item: &Option<Arc<T>>) -> u32 {
+        item.as_ref().map_or(0, |item| self.get(item))
+    }
+
+    /// All events, ordered by ID
+    pub fn sorted(&self) -> Vec<(u32, &Arc<T>)> {
+        let mut items: Vec<_> = self.items.iter().map(|(id, item)| (*id, item)).collect();
+        items.sort_by_key(|(id, _)| *id);
+        items
+    }
+}
+
+/// Shapes are additionally deduplicated by content, because identical
+/// shapes are often used by different events.
+struct ShapeTable {
+    ids: HashMap<Vec<u64>, u32>,
+    items: Vec<(u32, Arc<Shape>)>,
+}
+
+impl ShapeTable {
+    fn new<'a>(shapes: impl IntoIterator<Item = &'a Arc<Shape>>) -> Self {
+        let key =
+            |shape: &Shape| -> Vec<u64> { shape.samples.iter().map(|x| x.to_bits()).collect() };
+        // Unique shapes with the original ID of one of their copies
+        let mut index = HashMap::new();
+        let mut unique: Vec<(&Arc<Shape>, Option<u32>)> = Vec::new();
+        for shape in shapes {
+            // Expanded shapes are written without their time shape, so they
+            // differ from the shape that had the original ID
+            let source_id = shape.id.filter(|_| shape.time_id.is_none());
+            let i = *index.entry(key(shape)).or_insert_with(|| {
+                unique.push((shape, None));
+                unique.len() - 1
+            });
+            unique[i].1 = unique[i].1.or(source_id);
+        }
+        let source_ids: Vec<_> = unique.iter().map(|(_, id)| *id).collect();
+
+        let items: Vec<_> = (assign_ids(&source_ids).into_iter())
+            .zip(unique)
+            .map(|(id, (shape, _))| (id, shape.clone()))
+            .collect();
+        let ids = items.iter().map(|(id, shape)| (key(shape), *id)).collect();
+        Self { ids, items }
+    }
+
+    /// ID of a shape with the content of one that was passed to `new`
+    fn get(&self, shape: &Shape) -> u32 {
+        self.ids[&shape
+            .samples
+            .iter()
+            .map(|x| x.to_bits())
+            .collect::<Vec<_>>()]
+    }
+
+    fn sorted(&self) -> Vec<&(u32, Arc<Shape>)> {
+        let mut items: Vec<_> = self.items.iter().collect();
+        items.sort_by_key(|(id, _)| *id);
+        items
+    }
+}
+
+struct ExtensionSpec {
+    name: String,
+    objects: Vec<String>,
+}
+
+/// Extension objects are deduplicated by content. Lists are built back to
+/// front, so that blocks with identical lists or tails share references.
+#[derive(Default)]
+struct ExtensionTable {
+    specs: Vec<ExtensionSpec>,
+    objects: HashMap<(String, String), (u32, u32)>,
+    /// (spec_id, obj_id, next)
+    refs: Vec<(u32, u32, u32)>,
+    ref_ids: HashMap<(u32, u32, u32), u32>,
+}
+
+impl ExtensionTable {
+    fn insert_object(&mut self, name: &str, data: String) -> (u32, u32) {
+        let key = (name.to_owned(), data);
+        if let Some(ids) = self.objects.get(&key) {
+            return *ids;
+        }
+
+        let spec_id = match self.specs.iter().position(|spec| spec.name == name) {
+            Some(index) => index,
+            None => {
+                self.specs.push(ExtensionSpec {
+                    name: name.to_owned(),
+                    objects: Vec::new(),
+                });
+                self.specs.len() - 1
+            }
+        };
+        let objects = &mut self.specs[spec_id].objects;
+        objects.push(key.1.clone());
+        let ids = (spec_id as u32 + 1, objects.len() as u32);
+        self.objects.insert(key, ids);
+        ids
+    }
+
+    /// Returns the ID of the first element of the extension list of the block
+    fn insert_block(&mut self, block: &Block) -> u32 {
+        let mut list = Vec::new();
+        for trigger in &block.triggers {
+            let ty = match trigger.ty {
+                TriggerType::Output => 1,
+                TriggerType::Physio => 2,
+            };
+            let data = format!(
+                "{ty} {} {} {}",
+                trigger.channel,
+                us(trigger.delay),
+                us(trigger.duration)
+            );
+            list.push(self.insert_object("TRIGGERS", data));
+        }
+        if let Some(rot) = &block.rotation {
+            let [w, x, y, z] = rot.quaternion;
+            list.push(self.insert_object("ROTATIONS", format!("{w} {x} {y} {z}")));
+        }
+        if let Some(delay) = &block.soft_delay {
+            let data = format!(
+                "{} {} {} {}",
+                delay.num,
+                us(delay.offset),
+                delay.factor,
+                delay.hint
+            );
+            list.push(self.insert_object("DELAYS", data));
+        }
+        for label in &block.labels {
+            let name = match label.op {
+                LabelOp::Set => "LABELSET",
+                LabelOp::Inc => "LABELINC",
+            };
+            list.push(self.insert_object(name, format!("{} {}", label.value, label.name)));
+        }
+        for ext in &block.extensions {
+            list.push(self.insert_object(&ext.name, ext.data.clone()));
+        }
+
+        let mut next = 0;
+        for (spec_id, obj_id) in list.into_iter().rev() {
+            let key = (spec_id, obj_id, next);
+            next = *self.ref_ids.entry(key).or_insert_with(|| {
+                self.refs.push(key);
+                self.refs.len() as u32
+            });
+        }
+        next
+    }
+}
+
+/// Compresses a shape like pypulseq: samples are quantized to 1e-7, then the
+/// derivative is run length encoded. Repeated values are followed by the
+/// number of additional repetitions. Returns the uncompressed samples if
+/// compression doesn't make the shape shorter, because the sample count is
+/// how readers detect compression.
+fn compress_shape(samples: &[f64]) -> Vec<String> {
+    let quantized: Vec<i64> = samples.iter().map(|x| (x * 1e7).round() as i64).collect();
+    let deriv: Vec<i64> = quantized
+        .iter()
+        .scan(0, |prev, &x| {
+            let d = x - *prev;
+            *prev = x;
+            Some(d)
+        })
+        .collect();
+
+    let mut compressed = Vec::new();
+    let mut i = 0;
+    while i < deriv.len() {
+        let value = deriv[i];
+        let run = deriv[i..].iter().take_while(|&&x| x == value).count();
+        if run >= 2 {
+            compressed.push(format!("{value}e-7"));
+            compressed.push(format!("{value}e-7"));
+            compressed.push((run - 2).to_string());
+        } else {
+            compressed.push(format!("{value}e-7"));
+        }
+        i += run;
+    }
+
+    if compressed.len() < samples.len() {
+        compressed
+    } else {
+        samples.iter().map(|x| format!("{x:e}")).collect()
+    }
+}
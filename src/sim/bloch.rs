@@ -0,0 +1,158 @@
+// Discrete Bloch simulation of isochromats
+use std::f64::consts::TAU;
+
+use super::Isochromat;
+use crate::{Block, Sequence, TimeRaster};
+
+/// Simulates the isochromats and returns the sum of their transverse
+/// magnetization `(re, im)` for every ADC sample, in the order of the blocks.
+///
+/// Every block is split at the RF raster, gradient corners and ADC samples.
+/// Within these intervals, RF is constant and gradients are linear, so they
+/// are applied as a single rotation followed by relaxation. Gradients are
+/// rotated by the ROTATIONS extension, ppm offsets are ignored.
+///
+/// Precession is counterclockwise for positive frequencies, matching the
+/// RF and ADC frequency offsets: a 90° pulse with a phase of 0 rotates the
+/// magnetization from z to -y. ADC samples are demodulated with the ADC
+/// phase (and phase shape) and with its frequency offset, relative to the
+/// start of the ADC.
+pub fn bloch(seq: &Sequence, isochromats: &[Isochromat]) -> Vec<(f64, f64)> {
+    let timeline = seq.timeline();
+    let mut magnetization: Vec<[f64; 3]> =
+        isochromats.iter().map(|iso| [0.0, 0.0, iso.m0]).collect();
+    let mut signal = Vec::new();
+
+    for (index, block) in seq.blocks.iter().enumerate() {
+        let duration = timeline.block_end(index) - timeline.block_start(index);
+        let steps = block_steps(block, duration, &seq.time_raster);
+
+        let offset = signal.len();
+        if let Some(adc) = &block.adc {
+            signal.resize(offset + adc.num as usize, (0.0, 0.0));
+        }
+
+        for (iso, m) in isochromats.iter().zip(&mut magnetization) {
+            for step in &steps {
+                let off_resonance =
+                    iso.off_resonance + (0..3).map(|i| step.grad[i] * iso.position[i]).sum::<f64>();
+                let omega = [TAU * step.b1.0, TAU * step.b1.1, TAU * off_resonance];
+                rotate(m, omega, step.dt);
+
+                let e1 = (-step.dt / iso.t1).exp();
+                let e2 = (-step.dt / iso.t2).exp();
+                m[0] *= e2;
+                m[1] *= e2;
+                m[2] = iso.m0 + (m[2] - iso.m0) * e1;
+
+                if let Some((sample, phase)) = step.adc {
+                    let (sin, cos) = (-phase).sin_cos();
+                    let s = &mut signal[offset + sample];
+                    s.0 += m[0] * cos - m[1] * sin;
+                    s.1 += m[0] * sin + m[1] * cos;
+                }
+            }
+        }
+    }
+
+    signal
+}
+
+/// An interval of constant RF and linear gradients
+pub(super) struct Step {
+    /// Unit: `[s]`
+    pub(super) dt: f64,
+    /// RF `(re, im)` in `[Hz]`
+    pub(super) b1: (f64, f64),
+    /// Average gradients in `[Hz/m]`, rotated into the physical frame
+    pub(super) grad: [f64; 3],
+    /// ADC sample taken at the end of this step: index within the ADC and
+    /// demodulation phase in `[rad]`
+    adc: Option<(usize, f64)>,
+}
+
+pub(super) fn block_steps(block: &Block, duration: f64, raster: &TimeRaster) -> Vec<Step> {
+    let grads = [&block.gx, &block.gy, &block.gz];
+
+    // Points in time where the state changes or is sampled
+    let mut times = vec![0.0, duration];
+    if let Some(rf) = &block.rf {
+        let count = rf.amp_shape.samples.len();
+        times.extend((0..=count).map(|i| rf.delay + i as f64 * raster.rf));
+    }
+    for grad in grads.into_iter().flatten() {
+        times.extend(grad.breakpoints(raster.grad).iter().map(|p| p.0));
+    }
+    let adc_times: Vec<f64> = block.adc.as_ref().map_or(Vec::new(), |adc| {
+        (0..adc.num)
+            .map(|i| adc.delay + (i as f64 + 0.5) * adc.dwell)
+            .collect()
+    });
+    times.extend(&adc_times);
+
+    times.retain(|&t| (0.0..=duration).contains(&t));
+    times.sort_by(f64::total_cmp);
+    // Merge points that only differ by rounding errors
+    times.dedup_by(|b, a| *b - *a < 1e-12);
+
+    let matrix = block.rotation.as_ref().map(|rot| rot.matrix());
+    let mut next_adc = 0;
+
+    times
+        .windows(2)
+        .map(|w| {
+            let t = 0.5 * (w[0] + w[1]);
+            let b1 = block
+                .rf
+                .as_ref()
+                .and_then(|rf| rf.sample(t, raster.rf))
+                .map_or((0.0, 0.0), |(amp, phase)| {
+                    (amp * phase.cos(), amp * phase.sin())
+                });
+
+            let logical = grads.map(|g| g.as_ref().map_or(0.0, |g| g.sample(t, raster.grad)));
+            let grad = match matrix {
+                Some(r) => std::array::from_fn(|i| (0..3).map(|j| r[i][j] * logical[j]).sum()),
+                None => logical,
+            };
+
+            let mut adc = None;
+            if let (Some(&t_adc), Some(block_adc)) = (adc_times.get(next_adc), &block.adc) {
+                if (t_adc - w[1]).abs() < 1e-12 {
+                    let shape = (block_adc.phase_shape.as_ref())
+                        .and_then(|shape| shape.samples.get(next_adc))
+                        .map_or(0.0, |x| TAU * x);
+                    let phase =
+                        block_adc.phase + shape + TAU * block_adc.freq * (t_adc - block_adc.delay);
+                    adc = Some((next_adc, phase));
+                    next_adc += 1;
+                }
+            }
+
+            Step {
+                dt: w[1] - w[0],
+                b1,
+                grad,
+                adc,
+            }
+        })
+        .collect()
+}
+
+/// Rotates `m` counterclockwise about `omega` (in `[rad/s]`) for `dt`
+pub(super) fn rotate(m: &mut [f64; 3], omega: [f64; 3], dt: f64) {
+    let norm = (omega[0] * omega[0] + omega[1] * omega[1] + omega[2] * omega[2]).sqrt();
+    let angle = norm * dt;
+    if angle == 0.0 {
+        return;
+    }
+    let n = omega.map(|x| x / norm);
+    let (sin, cos) = angle.sin_cos();
+    let dot = n[0] * m[0] + n[1] * m[1] + n[2] * m[2];
+    let cross = [
+        n[1] * m[2] - n[2] * m[1],
+        n[2] * m[0] - n[0] * m[2],
+        n[0] * m[1] - n[1] * m[0],
+    ];
+    *m = std::array::from_fn(|i| m[i] * cos + cross[i] * sin + n[i] * dot * (1.0 - cos));
+}
@@ -0,0 +1,196 @@
+// Extended phase graph (EPG) simulation
+use std::{collections::HashMap, f64::consts::TAU};
+
+use super::Isochromat;
+use crate::{sequence::Complex, Rf, Sequence};
+
+/// Simulates the sequence with extended phase graphs and returns the echo
+/// amplitude `(re, im)` at the center of every ADC, in the order of the blocks.
+///
+/// RF pulses are instantaneous rotations at their center, with the flip
+/// angle and phase of their complex integral; frequency offsets are ignored.
+/// Gradients shift the dephasing states by their area, which is counted in
+/// multiples of `unit_area` (unit: `[1/m]`) per axis and rounded. Areas are
+/// accumulated from the start of the sequence, so rounding errors don't add
+/// up. The `position` of the `tissue` is not used, as the states describe
+/// the magnetization of the whole voxel. Conventions are the same as for
+/// `bloch`, ADCs are demodulated with their phase.
+pub fn epg(seq: &Sequence, tissue: &Isochromat, unit_area: f64) -> Vec<(f64, f64)> {
+    let timeline = seq.timeline();
+    let raster = &seq.time_raster;
+
+    let mut states = States::new(tissue.m0);
+    // Gradient moment since the start of the sequence and its dephasing order
+    let mut moment = [0.0; 3];
+    let mut order = [0i64; 3];
+    let mut signal = Vec::new();
+
+    for (index, block) in seq.blocks.iter().enumerate() {
+        let duration = timeline.block_end(index) - timeline.block_start(index);
+
+        let mut events = Vec::new();
+        if let Some(rf) = &block.rf {
+            let t = rf.delay + rf.center_or_peak(raster.rf);
+            events.push((t, Some(Instant::Rf(rf))));
+        }
+        if let Some(adc) = &block.adc {
+            let t = adc.delay + 0.5 * adc.num as f64 * adc.dwell;
+            events.push((t, Some(Instant::Adc(adc.phase))));
+        }
+        events.sort_by(|a, b| a.0.total_cmp(&b.0));
+        events.push((duration, None));
+
+        let points = [&block.gx, &block.gy, &block.gz].map(|g| {
+            g.as_ref()
+                .map_or(Vec::new(), |g| g.breakpoints(raster.grad))
+        });
+        let matrix = block.rotation.as_ref().map(|rot| rot.matrix());
+        let block_moment = moment;
+
+        let mut t = 0.0;
+        for (t_event, event) in events {
+            let logical: [f64; 3] = std::array::from_fn(|i| integral(&points[i], t_event));
+            let physical = match matrix {
+                Some(r) => std::array::from_fn(|i| (0..3).map(|j| r[i][j] * logical[j]).sum()),
+                None => logical,
+            };
+            moment = std::array::from_fn(|i| block_moment[i] + physical[i]);
+            let new_order = moment.map(|m| (m / unit_area).round() as i64);
+
+            states.relax(t_event - t, tissue);
+            states.shift(std::array::from_fn(|i| new_order[i] - order[i]));
+            order = new_order;
+            t = t_event;
+
+            match event {
+                Some(Instant::Rf(rf)) => {
+                    let (flip, phase) = rotation(rf, raster.rf);
+                    states.rf(flip, phase);
+                }
+                Some(Instant::Adc(phase)) => {
+                    let s = states.f0().mul(Complex::polar(1.0, -phase));
+                    signal.push((s.re, s.im));
+                }
+                None => (),
+            }
+        }
+    }
+
+    signal
+}
+
+enum Instant<'a> {
+    Rf(&'a Rf),
+    /// Phase of the ADC
+    Adc(f64),
+}
+
+/// Flip angle and phase of the pulse, both in `[rad]`
+fn rotation(rf: &Rf, rf_raster: f64) -> (f64, f64) {
+    let area = rf.integral(rf_raster);
+    (TAU * area.abs(), rf.phase + area.arg())
+}
+
+/// Integral of a piecewise linear waveform from its start until `t`
+fn integral(points: &[(f64, f64)], t: f64) -> f64 {
+    points
+        .windows(2)
+        .map(|w| {
+            let [(t0, g0), (t1, g1)] = [w[0], w[1]];
+            let end = t.min(t1);
+            if end <= t0 {
+                0.0
+            } else {
+                let g = g0 + (g1 - g0) * (end - t0) / (t1 - t0);
+                0.5 * (g0 + g) * (end - t0)
+            }
+        })
+        .sum()
+}
+
+// Dephasing orders per axis
+type Order = [i64; 3];
+
+/// Transverse states `F(k)` and longitudinal states `Z(k)`. The
+/// magnetization at position `r` is `Mxy = sum F(k) exp(i k r)`, likewise
+/// for `Mz`; `F-` states of the usual notation are `conj(F(-k))`.
+struct States {
+    m0: f64,
+    f: HashMap<Order, Complex>,
+    z: HashMap<Order, Complex>,
+}
+
+// States below this magnitude (relative to m0) are dropped
+const THRESHOLD: f64 = 1e-10;
+
+impl States {
+    fn new(m0: f64) -> Self {
+        Self {
+            m0,
+            f: HashMap::new(),
+            z: HashMap::from([([0; 3], Complex { re: m0, im: 0.0 })]),
+        }
+    }
+
+    fn f0(&self) -> Complex {
+        self.f.get(&[0; 3]).copied().unwrap_or_default()
+    }
+
+    fn shift(&mut self, delta: Order) {
+        if delta != [0; 3] {
+            self.f = (self.f.drain())
+                .map(|(k, f)| ([k[0] + delta[0], k[1] + delta[1], k[2] + delta[2]], f))
+                .collect();
+        }
+    }
+
+    fn relax(&mut self, dt: f64, tissue: &Isochromat) {
+        let e1 = (-dt / tissue.t1).exp();
+        let e2 = (-dt / tissue.t2).exp();
+        let precession = Complex::polar(e2, TAU * tissue.off_resonance * dt);
+        for f in self.f.values_mut() {
+            *f = f.mul(precession);
+        }
+        for z in self.z.values_mut() {
+            *z = z.scale(e1);
+        }
+        let z0 = self.z.entry([0; 3]).or_default();
+        z0.re += tissue.m0 * (1.0 - e1);
+    }
+
+    /// Instantaneous rotation, with the transition matrix of Weigel (2015)
+    fn rf(&mut self, flip: f64, phase: f64) {
+        let (sin, cos) = flip.sin_cos();
+        let cos2 = (0.5 * flip).cos().powi(2);
+        let f_fm = Complex::polar((0.5 * flip).sin().powi(2), 2.0 * phase);
+        // -i exp(i phase) sin(flip)
+        let f_z = Complex::polar(sin, phase - 0.25 * TAU);
+        // -i/2 exp(-i phase) sin(flip) and its conjugate
+        let z_f = Complex::polar(0.5 * sin, -phase - 0.25 * TAU);
+        let z_fm = z_f.conj();
+
+        let mut keys: Vec<Order> = self.f.keys().flat_map(|k| [*k, k.map(|x| -x)]).collect();
+        keys.extend(self.z.keys());
+        keys.sort_unstable();
+        keys.dedup();
+
+        let mut f = HashMap::with_capacity(keys.len());
+        let mut z = HashMap::with_capacity(keys.len());
+        for k in keys {
+            let fp = self.f.get(&k).copied().unwrap_or_default();
+            let fm = (self.f.get(&k.map(|x| -x)).copied().unwrap_or_default()).conj();
+            let zk = self.z.get(&k).copied().unwrap_or_default();
+
+            let new_f = fp.scale(cos2).add(f_fm.mul(fm)).add(f_z.mul(zk));
+            let new_z = z_f.mul(fp).add(z_fm.mul(fm)).add(zk.scale(cos));
+            f.insert(k, new_f);
+            z.insert(k, new_z);
+        }
+
+        let threshold = THRESHOLD * self.m0.abs();
+        f.retain(|_, x| x.abs() > threshold);
+        z.retain(|k, x| *k == [0; 3] || x.abs() > threshold);
+        self.f = f;
+        self.z = z;
+    }
+}
@@ -0,0 +1,38 @@
+// Signal simulation of sequences, enabled by the "sim" feature
+mod bloch;
+mod epg;
+mod slice_profile;
+
+pub use bloch::bloch;
+pub use epg::epg;
+pub use slice_profile::{slice_profile, SliceProfile};
+
+/// A group of spins with identical properties, as simulated by `bloch`, or
+/// the tissue of a voxel for `epg`.
+/// Magnetization starts in equilibrium: `[0, 0, m0]`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Isochromat {
+    /// Unit: `[m]`
+    pub position: [f64; 3],
+    /// Unit: `[s]`, can be infinite
+    pub t1: f64,
+    /// Unit: `[s]`, can be infinite
+    pub t2: f64,
+    /// Unit: `[Hz]`
+    pub off_resonance: f64,
+    /// Equilibrium magnetization, e.g. the proton density
+    pub m0: f64,
+}
+
+impl Isochromat {
+    /// Isochromat at the origin without off-resonance and with `m0` of 1
+    pub fn new(t1: f64, t2: f64) -> Self {
+        Self {
+            position: [0.0; 3],
+            t1,
+            t2,
+            off_resonance: 0.0,
+            m0: 1.0,
+        }
+    }
+}
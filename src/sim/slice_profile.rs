@@ -0,0 +1,112 @@
+// Slice profiles of RF pulses by Bloch simulation
+use std::f64::consts::TAU;
+
+use super::bloch::{block_steps, rotate};
+use crate::{Block, TimeRaster};
+
+/// Magnetization after a block with an RF pulse, over the slice direction
+#[derive(Debug, Clone, PartialEq)]
+pub struct SliceProfile {
+    /// Positions along the logical z axis. Unit: `[m]`
+    pub positions: Vec<f64>,
+    /// Magnetization `[x, y, z]` at the end of the block for every position,
+    /// starting from `[0, 0, 1]`
+    pub magnetization: Vec<[f64; 3]>,
+}
+
+/// Simulates the RF pulse of the `block` together with its gradients for
+/// isochromats along the slice select axis `gz`, without relaxation and in
+/// the rotating frame of the RF pulse. `positions` are sorted ascending and
+/// given in the logical frame, the ROTATIONS extension is respected.
+///
+/// The frequency offset of the pulse shifts the slice by `freq / gz`. The
+/// magnetization is taken at the end of the block, so the slice rephasing
+/// gradient, which is usually played in the next block, is not applied.
+/// Returns `None` if the block contains no RF pulse.
+pub fn slice_profile(
+    block: &Block,
+    raster: &TimeRaster,
+    positions: &[f64],
+) -> Option<SliceProfile> {
+    let rf = block.rf.as_ref()?;
+    let duration = [&block.gx, &block.gy, &block.gz]
+        .into_iter()
+        .flatten()
+        .map(|grad| grad.duration(raster.grad))
+        .fold(block.duration.max(rf.duration(raster.rf)), f64::max);
+    let steps = block_steps(block, duration, raster);
+    // Physical direction of the logical slice axis
+    let axis = match &block.rotation {
+        Some(rot) => rot.matrix().map(|row| row[2]),
+        None => [0.0, 0.0, 1.0],
+    };
+
+    let magnetization = positions
+        .iter()
+        .map(|&z| {
+            let mut m = [0.0, 0.0, 1.0];
+            for step in &steps {
+                let off_resonance: f64 = (0..3).map(|i| step.grad[i] * axis[i] * z).sum();
+                let omega = [TAU * step.b1.0, TAU * step.b1.1, TAU * off_resonance];
+                rotate(&mut m, omega, step.dt);
+            }
+            m
+        })
+        .collect();
+
+    Some(SliceProfile {
+        positions: positions.to_vec(),
+        magnetization,
+    })
+}
+
+impl SliceProfile {
+    /// Magnitude of the transverse magnetization for every position
+    pub fn transverse(&self) -> Vec<f64> {
+        (self.magnetization.iter())
+            .map(|m| m[0].hypot(m[1]))
+            .collect()
+    }
+
+    /// Full width at half maximum of the transverse magnetization, with the
+    /// crossings interpolated between positions. Unit: `[m]`
+    pub fn thickness(&self) -> f64 {
+        let (start, end) = self.half_max();
+        end - start
+    }
+
+    /// Middle between the half maximum crossings of the transverse
+    /// magnetization. Unit: `[m]`
+    pub fn center(&self) -> f64 {
+        let (start, end) = self.half_max();
+        0.5 * (start + end)
+    }
+
+    /// Positions where the transverse magnetization falls below half of its
+    /// maximum, searching outwards from the maximum. If it doesn't, the first
+    /// or last position is used.
+    fn half_max(&self) -> (f64, f64) {
+        let values = self.transverse();
+        let Some((peak, max)) =
+            (values.iter().copied().enumerate()).max_by(|a, b| a.1.total_cmp(&b.1))
+        else {
+            return (0.0, 0.0);
+        };
+        let half = 0.5 * max;
+        let crossing = |inside: usize, outside: usize| {
+            let (a, b) = (values[inside], values[outside]);
+            let (pa, pb) = (self.positions[inside], self.positions[outside]);
+            pa + (pb - pa) * (a - half) / (a - b)
+        };
+
+        let start = match (0..peak).rev().find(|&i| values[i] < half) {
+            Some(i) => crossing(i + 1, i),
+            None => self.positions[0],
+        };
+        let end = match (peak + 1..values.len()).find(|&i| values[i] < half) {
+            Some(i) => crossing(i - 1, i),
+            None => self.positions[values.len() - 1],
+        };
+        (start, end)
+    }
+}
@@ -0,0 +1,73 @@
+use std::sync::Arc;
+
+use pulseq_rs::{Adc, Block, Gradient, Rf, Sequence, SequenceBuilder, TimeRaster};
+
+#[test]
+fn build() {
+    let mut builder = SequenceBuilder::new(TimeRaster::default())
+        .name("builder")
+        .fov(0.2, 0.2, 0.005);
+
+    let rf = Arc::new(Rf::from_real(&[100.0, -200.0, 100.0], 5e-6));
+    let readout = Arc::new(Gradient::trap(1e5, 20e-6, 640e-6, 20e-6, 0.0));
+    let adc = Arc::new(Adc::new(64, 10e-6, 20e-6));
+
+    assert_eq!(
+        builder.add_block(Block {
+            rf: Some(rf.clone()),
+            ..Default::default()
+        }),
+        1
+    );
+    for _ in 0..2 {
+        builder.add_block(Block {
+            gx: Some(readout.clone()),
+            adc: Some(adc.clone()),
+            ..Default::default()
+        });
+    }
+    assert_eq!(builder.add_delay(1e-3), 4);
+    let seq = builder.build().unwrap();
+
+    let ids: Vec<_> = seq.blocks.iter().map(|b| b.id).collect();
+    assert_eq!(ids, [1, 2, 3, 4]);
+    // 8 us of RF are rounded up to the block raster of 10 us
+    assert!((seq.blocks[0].duration - 10e-6).abs() < 1e-12);
+    assert!((seq.blocks[1].duration - 680e-6).abs() < 1e-12);
+    assert!((seq.blocks[3].duration - 1e-3).abs() < 1e-12);
+
+    let rf = seq.blocks[0].rf.as_ref().unwrap();
+    assert_eq!(rf.amp, 200.0);
+    assert_eq!(rf.amp_shape.samples, [0.5, 1.0, 0.5]);
+    assert_eq!(rf.phase_shape.samples, [0.0, 0.5, 0.0]);
+
+    // Shared events are written once and the file can be loaded again
+    let source = seq.to_source();
+    let reloaded = Sequence::from_source(&source).unwrap();
+    assert_eq!(reloaded.name.as_deref(), Some("builder"));
+    assert_eq!(reloaded.blocks.len(), 4);
+    assert!(Arc::ptr_eq(
+        reloaded.blocks[1].adc.as_ref().unwrap(),
+        reloaded.blocks[2].adc.as_ref().unwrap()
+    ));
+}
+
+#[test]
+fn free_gradient() {
+    let grad = Gradient::free(&[0.0, -500.0, 1000.0], 0.0);
+    let Gradient::Free { amp, shape, .. } = &grad else {
+        panic!("expected a free gradient");
+    };
+    assert_eq!(*amp, 1000.0);
+    assert_eq!(shape.samples, [0.0, -0.5, 1.0]);
+}
+
+#[test]
+fn invalid() {
+    let mut builder = SequenceBuilder::new(TimeRaster::default());
+    builder.add_block(Block {
+        gx: Some(Arc::new(Gradient::trap(1e5, -10e-6, 0.0, 10e-6, 0.0))),
+        ..Default::default()
+    });
+    assert!(builder.build().is_err());
+}
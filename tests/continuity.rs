@@ -0,0 +1,107 @@
+use pulseq_rs::{EventType, Sequence, ValidationError};
+
+const SEQ: &str = "
+[VERSION]
+major 1
+minor 4
+revision 1
+
+[DEFINITIONS]
+AdcRasterTime 1e-07
+BlockDurationRaster 1e-05
+GradientRasterTime 1e-05
+RadiofrequencyRasterTime 1e-06
+
+[BLOCKS]
+1 20 0 1 0 0 0 0
+2 20 0 2 0 0 0 0
+3 10 0 3 0 0 0 0
+4 2 0 4 0 0 0 0
+
+[GRADIENTS]
+1 1000 1 3 0
+2 1000 2 3 0
+4 1000 4 5 0
+
+[TRAP]
+3 1000 20 40 20 10
+
+[SHAPES]
+
+shape_id 1
+num_samples 3
+0
+1
+1
+
+shape_id 2
+num_samples 3
+1
+1
+0
+
+shape_id 3
+num_samples 3
+0
+10
+20
+
+shape_id 4
+num_samples 2
+0.5
+0.5
+
+shape_id 5
+num_samples 2
+0
+2
+";
+
+#[test]
+fn jumps() {
+    let seq = Sequence::from_source(SEQ).unwrap();
+
+    // Extended trapezoids keep their exact first and last amplitude
+    assert_eq!(seq.blocks[0].gx.as_ref().unwrap().first(), 0.0);
+    assert_eq!(seq.blocks[0].gx.as_ref().unwrap().last(), 1000.0);
+
+    // Blocks 1 - 3 are continuous, the constant gradient in block 4 is not
+    let errors = seq.check_continuity(1.0);
+    assert_eq!(errors.len(), 2, "{errors:?}");
+    assert!(matches!(
+        errors[0],
+        ValidationError::GradientJump {
+            ty: EventType::Gx,
+            block_id: 4,
+            time: 0.0,
+            before: 0.0,
+            after: 500.0,
+        }
+    ));
+    assert!(matches!(
+        errors[1],
+        ValidationError::GradientJump {
+            ty: EventType::Gx,
+            block_id: 4,
+            before: 500.0,
+            after: 0.0,
+            ..
+        }
+    ));
+
+    assert!(seq.check_continuity(500.0).is_empty());
+}
+
+#[test]
+fn arbitrary_pre_1_5() {
+    // Before pulseq 1.5, arbitrary gradients don't store their first and
+    // last amplitude, which are 0 like in pypulseq
+    let source = SEQ.replace("4 1000 4 5 0", "4 1000 4 0 0");
+    let seq = Sequence::from_source(&source).unwrap();
+    let grad = seq.blocks[3].gx.as_ref().unwrap();
+    assert_eq!(grad.first(), 0.0);
+    assert_eq!(grad.last(), 0.0);
+    assert_eq!(grad.sample(0.0, 1e-5), 0.0);
+    assert_eq!(grad.sample(5e-6, 1e-5), 500.0);
+    assert!(seq.check_continuity(1.0).is_empty());
+}
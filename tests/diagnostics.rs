@@ -0,0 +1,159 @@
+use pulseq_rs::{parse_file, ConversionError, Error, EventType, Sequence, ValidationError};
+
+const BROKEN_REF: &str = "[VERSION]
+major 1
+minor 3
+revision 1
+
+[BLOCKS]
+1 0 0 1 0 0 0 0
+2 0 0 1 2 0 0 0
+
+[TRAP]
+1 1000 10 20 10 0
+";
+
+#[test]
+fn broken_ref() {
+    let Err(Error::Located { error, location }) = Sequence::from_source(BROKEN_REF) else {
+        panic!("expected a located error");
+    };
+    assert!(matches!(
+        *error,
+        Error::ConversionError(ConversionError::BrokenRef {
+            ty: EventType::Gy,
+            id: 2
+        })
+    ));
+    assert_eq!(location.line, 8);
+    assert_eq!(location.column, 9);
+    assert_eq!(location.snippet, "2 0 0 1 2 0 0 0");
+
+    let rendered = format!("{location}");
+    assert_eq!(
+        rendered,
+        " --> line 8:9
+  |
+8 | 2 0 0 1 2 0 0 0
+  |         ^"
+    );
+}
+
+#[test]
+fn duplicate_id() {
+    let source = "[VERSION]
+major 1
+minor 3
+revision 1
+
+[ADC]
+1 10 10000 0 0 0
+1 20 10000 0 0 0
+";
+    let Err(Error::Located { error, location }) = Sequence::from_source(source) else {
+        panic!("expected a located error");
+    };
+    assert!(matches!(
+        *error,
+        Error::ConversionError(ConversionError::EventIdReuse(_))
+    ));
+    assert_eq!(location.line, 8);
+    assert_eq!(location.column, 1);
+}
+
+#[test]
+fn event_too_long() {
+    let source = "[VERSION]
+major 1
+minor 4
+revision 1
+
+[DEFINITIONS]
+AdcRasterTime 1e-07
+BlockDurationRaster 1e-05
+GradientRasterTime 1e-05
+RadiofrequencyRasterTime 1e-06
+
+[BLOCKS]
+1 2 0 0 0 1 0 0
+
+[TRAP]
+1 1000 10 20 10 0
+";
+    let Err(Error::Located { error, location }) = Sequence::from_source(source) else {
+        panic!("expected a located error");
+    };
+    assert!(matches!(
+        *error,
+        Error::ValidationError(ValidationError::EventTooLong {
+            ty: EventType::Gz,
+            block_id: 1,
+            ..
+        })
+    ));
+    assert_eq!(location.line, 13);
+    assert_eq!(location.column, 11);
+}
+
+#[test]
+fn extension_lines() {
+    // Comments, blank lines and indentation don't shift the reported line
+    let source = "[VERSION]
+major 1
+minor 4
+revision 1
+
+[DEFINITIONS]
+AdcRasterTime 1e-07
+BlockDurationRaster 1e-05
+GradientRasterTime 1e-05
+RadiofrequencyRasterTime 1e-06
+
+[BLOCKS]
+1 10 0 0 0 0 0 1
+
+[EXTENSIONS]
+# [id] [type] [ref] [next_id]
+  1 1 1 0
+
+# Extension specification
+extension TRIGGERS 1
+ 1 1 1 0 200
+";
+    let Err(err) = Sequence::from_source(source) else {
+        panic!("expected an error");
+    };
+    assert!(matches!(
+        err.inner(),
+        Error::ValidationError(ValidationError::EventTooLong {
+            ty: EventType::Trigger,
+            ..
+        })
+    ));
+    assert_eq!(err.location().unwrap().line, 13);
+
+    let source = source.replace(" 1 1 1 0 200", " 1 7 1 0 200");
+    let err = Sequence::from_source(&source).err().unwrap();
+    assert!(matches!(
+        err.inner(),
+        Error::ConversionError(ConversionError::InvalidExtensionData { .. })
+    ));
+    let location = err.location().unwrap();
+    assert_eq!(location.line, 21);
+    assert_eq!(location.snippet, " 1 7 1 0 200");
+}
+
+#[test]
+fn parsed_file() {
+    // Without the source, errors keep their plain shape
+    let sections = parse_file(BROKEN_REF).unwrap();
+    let err = Sequence::from_parsed_file(sections).err().unwrap();
+    assert!(err.location().is_none());
+    assert!(matches!(
+        err,
+        Error::ConversionError(ConversionError::BrokenRef {
+            ty: EventType::Gy,
+            id: 2
+        })
+    ));
+}
@@ -0,0 +1,290 @@
+use pulseq_rs::{
+    ConversionError, Error, ExtensionHandler, Label, LabelName, LabelOp, LoadOptions, SectionType,
+    Sequence, TriggerType, Warning,
+};
+
+const HEADER: &str = "
+[VERSION]
+major 1
+minor 4
+revision 1
+
+[DEFINITIONS]
+AdcRasterTime 1e-07
+BlockDurationRaster 1e-05
+GradientRasterTime 1e-05
+RadiofrequencyRasterTime 1e-06
+
+[BLOCKS]
+1 10 0 0 0 0 0 1
+2 10 0 0 0 0 0 3
+3 10 0 0 0 0 0 0
+";
+
+fn load(extensions: &str) -> Result<Sequence, pulseq_rs::Error> {
+    Sequence::from_source(&format!("{HEADER}\n[EXTENSIONS]\n{extensions}"))
+}
+
+#[test]
+fn linked_list() {
+    let seq = load(
+        "1 1 1 2
+2 2 1 0
+3 1 2 0
+extension FOO 1
+1 0 A
+2 1 B
+extension BAR 2
+1 1 C
+",
+    )
+    .unwrap();
+
+    let ext: Vec<_> = seq.blocks[0]
+        .extensions
+        .iter()
+        .map(|e| (e.name.as_str(), e.data.as_str()))
+        .collect();
+    assert_eq!(ext, [("FOO", "0 A"), ("BAR", "1 C")]);
+    assert_eq!(seq.blocks[1].extensions[0].data, "1 B");
+    assert!(seq.blocks[2].extensions.is_empty());
+}
+
+#[test]
+fn duplicate_object() {
+    let extensions = "1 1 1 0\n2 1 2 0\n3 1 1 0\nextension FOO 1\n1 0 A\n2 1 B\n1 2 C\n";
+    assert!(load(extensions).is_err());
+
+    let source = format!("{HEADER}\n[EXTENSIONS]\n{extensions}");
+    let options = LoadOptions::new().lenient(true);
+    let (seq, warnings) = Sequence::from_source_with_options(&source, &options).unwrap();
+    // The first object with a duplicated ID wins
+    assert_eq!(seq.blocks[0].extensions[0].data, "0 A");
+    assert_eq!(seq.blocks[1].extensions[0].data, "0 A");
+    assert!(matches!(
+        warnings[..],
+        [Warning::DuplicateId {
+            section: SectionType::Extensions,
+            id: 1,
+            line: Some(25)
+        }]
+    ));
+}
+
+#[test]
+fn labels() {
+    let seq = load(
+        "1 1 1 2
+2 2 1 0
+3 1 2 0
+extension LABELSET 1
+1 0 LIN
+2 1 NAV
+extension LABELINC 2
+1 1 LIN
+",
+    )
+    .unwrap();
+
+    assert_eq!(
+        seq.blocks[0].labels,
+        [
+            Label {
+                op: LabelOp::Set,
+                name: LabelName::Lin,
+                value: 0
+            },
+            Label {
+                op: LabelOp::Inc,
+                name: LabelName::Lin,
+                value: 1
+            }
+        ]
+    );
+    assert!(seq.blocks[0].extensions.is_empty());
+
+    assert!(load("1 2 1 0\nextension LABELINC 2\n1 1 NAV\n").is_err());
+    assert!(load("1 1 1 0\nextension LABELSET 1\n1 1 FOO\n").is_err());
+}
+
+#[test]
+fn uninterpreted_labels() {
+    let extensions = "1 1 1 2
+2 2 1 0
+3 3 1 0
+extension LABELSET 1
+1 1 FUTURE
+extension LABELINC 2
+1 1 NAV
+extension TRIGGERS 3
+1 3 1 0 10
+";
+    let err = load("1 1 1 0\nextension LABELSET 1\n1 1 FUTURE\n").unwrap_err();
+    assert!(matches!(
+        err.inner(),
+        Error::ConversionError(ConversionError::UnknownLabel(name)) if name == "FUTURE"
+    ));
+    let err = load("1 1 1 0\nextension LABELINC 1\n1 1 NAV\n").unwrap_err();
+    assert!(matches!(
+        err.inner(),
+        Error::ConversionError(ConversionError::IncrementedFlag(LabelName::Nav))
+    ));
+
+    let source = format!("{HEADER}\n[EXTENSIONS]\n{extensions}");
+    let options = LoadOptions::new().lenient(true);
+    let (seq, warnings) = Sequence::from_source_with_options(&source, &options).unwrap();
+    assert_eq!(warnings.len(), 3, "{warnings:#?}");
+    assert!(warnings
+        .iter()
+        .all(|w| matches!(w, Warning::UninterpretedExtension { .. })));
+
+    // Labels and triggers that can't be parsed are kept as they are
+    assert!(seq.blocks[0].labels.is_empty());
+    let ext: Vec<_> = seq.blocks[0]
+        .extensions
+        .iter()
+        .map(|e| (e.name.as_str(), e.data.as_str()))
+        .collect();
+    assert_eq!(ext, [("LABELSET", "1 FUTURE"), ("LABELINC", "1 NAV")]);
+    assert!(seq.blocks[1].triggers.is_empty());
+    assert_eq!(seq.blocks[1].extensions[0].name, "TRIGGERS");
+    assert_eq!(seq.blocks[1].extensions[0].data, "3 1 0 10");
+
+    let written = seq.to_source();
+    assert!(written.contains("\n1 1 FUTURE\n"), "{written}");
+}
+
+#[test]
+fn adc_labels() {
+    let seq = Sequence::from_source(&format!(
+        "{}
+[BLOCKS]
+1 10 0 0 0 0 0 1
+2 10 0 0 0 0 1 2
+3 10 0 0 0 0 1 2
+4 10 0 0 0 0 1 3
+
+[ADC]
+1 4 1000 0 0 0
+
+[EXTENSIONS]
+1 1 1 0
+2 2 1 0
+3 1 2 0
+extension LABELSET 1
+1 5 LIN
+2 1 NAV
+extension LABELINC 2
+1 1 LIN
+",
+        HEADER.split("[BLOCKS]").next().unwrap()
+    ))
+    .unwrap();
+
+    let labels: Vec<_> = seq
+        .adc_labels()
+        .into_iter()
+        .map(|(block, state)| {
+            (
+                block.id,
+                state.get(LabelName::Lin),
+                state.flag(LabelName::Nav),
+            )
+        })
+        .collect();
+    assert_eq!(labels, [(2, 6, false), (3, 7, false), (4, 7, true)]);
+}
+
+#[test]
+fn broken_ref() {
+    assert!(load("1 1 1 0\n3 1 2 4\nextension LABELSET 1\n1 0 LIN\n2 1 NAV\n").is_err());
+    assert!(load("1 1 1 0\n3 1 5 0\nextension LABELSET 1\n1 0 LIN\n").is_err());
+}
+
+#[test]
+fn cycle() {
+    assert!(load("1 1 1 3\n3 1 1 1\nextension LABELSET 1\n1 0 LIN\n").is_err());
+}
+
+#[test]
+fn triggers() {
+    // Objects are `id type channel delay duration`: type 1 is an output
+    // (osc0, osc1, ext1), type 2 a physio trigger (physio1, physio2)
+    let seq = load("1 1 1 0\n3 1 2 0\nextension TRIGGERS 1\n1 1 1 10 20\n2 2 2 0 50\n").unwrap();
+
+    let trigger = &seq.blocks[0].triggers[0];
+    assert_eq!(trigger.ty, TriggerType::Output);
+    assert_eq!(trigger.channel, 1);
+    assert!((trigger.total_duration() - 30e-6).abs() < 1e-12);
+    assert!(seq.blocks[0].extensions.is_empty());
+
+    let trigger = &seq.blocks[1].triggers[0];
+    assert_eq!(trigger.ty, TriggerType::Physio);
+    assert_eq!(trigger.channel, 2);
+
+    // The second trigger is longer than the block (100 us)
+    assert!(load("1 1 1 0\n3 1 2 0\nextension TRIGGERS 1\n1 2 1 10 20\n2 1 1 0 200\n").is_err());
+}
+
+#[test]
+fn pypulseq_triggers() {
+    // Layout of pypulseq's make_trigger("physio1") and
+    // make_digital_output_pulse("osc0" / "ext1")
+    let seq = Sequence::from_file("assets/triggers.seq").unwrap();
+    assert_eq!(seq.signature.as_ref().unwrap().is_valid(), Some(true));
+
+    let triggers: Vec<_> = (seq.blocks.iter())
+        .map(|block| (block.triggers[0].ty, block.triggers[0].channel))
+        .collect();
+    assert_eq!(
+        triggers,
+        [
+            (TriggerType::Physio, 1),
+            (TriggerType::Output, 1),
+            (TriggerType::Output, 3)
+        ]
+    );
+    assert!((seq.blocks[0].triggers[0].duration - 2e-3).abs() < 1e-12);
+
+    // Written back with the same type codes
+    let source = seq.to_source();
+    assert!(source.contains("1 2 1 0 2000\n2 1 1 10 100\n3 1 3 0 50\n"));
+}
+
+/// In-house extension storing a table position in mm
+struct TablePosition;
+
+impl ExtensionHandler for TablePosition {
+    type Output = f64;
+    type Error = std::num::ParseFloatError;
+
+    fn name(&self) -> &str {
+        "TABLE"
+    }
+
+    fn parse(&self, data: &str) -> Result<f64, Self::Error> {
+        data.trim().parse()
+    }
+}
+
+#[test]
+fn handler() {
+    let options = LoadOptions::new().extension(TablePosition);
+    let source = format!(
+        "{HEADER}\n[EXTENSIONS]\n1 1 1 2\n2 2 1 0\n3 1 2 0\nextension TABLE 1\n1 12.5\n2 -3\nextension FOO 2\n1 bar\n"
+    );
+    let (seq, _) = Sequence::from_source_with_options(&source, &options).unwrap();
+
+    let positions: Vec<_> = seq.blocks[0].extension_values::<f64>().collect();
+    assert_eq!(positions, [&12.5]);
+    assert_eq!(seq.blocks[1].extensions[0].value::<f64>(), Some(&-3.0));
+    // Unhandled extensions and values of other types are not affected
+    assert!(seq.blocks[0].extensions[1].value.is_none());
+    assert_eq!(seq.blocks[0].extensions[0].value::<u32>(), None);
+    assert_eq!(seq.blocks[0].extensions[0].data, "12.5");
+
+    let broken = source.replace("12.5", "twelve");
+    assert!(Sequence::from_source_with_options(&broken, &options).is_err());
+    // Without a handler, the data is not interpreted
+    assert!(Sequence::from_source(&broken).is_ok());
+}
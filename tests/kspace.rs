@@ -0,0 +1,80 @@
+use pulseq_rs::Sequence;
+
+const SEQ: &str = "
+[VERSION]
+major 1
+minor 4
+revision 1
+
+[DEFINITIONS]
+AdcRasterTime 1e-07
+BlockDurationRaster 1e-05
+GradientRasterTime 1e-05
+RadiofrequencyRasterTime 1e-06
+
+[BLOCKS]
+1 3 0 0 1 0 0 0
+2 10 1 0 0 0 0 0
+3 10 0 2 0 0 0 0
+4 18 0 3 0 0 1 0
+5 10 2 0 0 0 0 0
+6 1 0 0 0 0 2 0
+
+[RF]
+1 1000 1 2 0 0 0 0
+2 100000 1 2 0 0 0 0
+
+[TRAP]
+1 1000 10 10 10 0
+2 -1000 10 80 10 0
+3 1000 10 160 10 0
+
+[ADC]
+1 16 10000 10 0 0
+2 1 10000 0 0 0
+
+[SHAPES]
+
+shape_id 1
+num_samples 4
+1
+1
+1
+1
+
+shape_id 2
+num_samples 4
+0
+0
+0
+0
+";
+
+fn assert_close(a: [f64; 3], b: [f64; 3]) {
+    for i in 0..3 {
+        assert!((a[i] - b[i]).abs() < 1e-9, "{a:?} != {b:?}");
+    }
+}
+
+#[test]
+fn readout() {
+    let seq = Sequence::from_source(SEQ).unwrap();
+    let kspace = seq.kspace();
+
+    // Small flip angle: excitation, large: refocusing. Both centered at 2 us
+    assert_eq!(kspace.t_excitation.len(), 1);
+    assert!((kspace.t_excitation[0] - 32e-6).abs() < 1e-12);
+    assert_eq!(kspace.t_refocusing.len(), 1);
+    assert_eq!(kspace.t_adc.len(), 17);
+    assert_eq!(kspace.k.len(), kspace.t.len());
+
+    // The gy lobe before the excitation is reset, the readout is centered
+    // around sample 8 after the -0.09 / m prephaser
+    for (i, &k) in kspace.k_adc[..16].iter().enumerate() {
+        assert_close(k, [-0.085 + 0.01 * (i as f64 + 0.5), 0.0, 0.0]);
+    }
+    assert_close(kspace.k_adc[8], [0.0, 0.0, 0.0]);
+
+    // Readout ends at 0.08 / m, which is inverted by the refocusing pulse
+    assert_close(kspace.k_adc[16], [-0.08, 0.0, 0.0]);
+}
@@ -0,0 +1,116 @@
+use pulseq_rs::{LoadOptions, MissingDefinition, SectionType, Sequence, ValidationError, Warning};
+
+const QUIRKY: &str = "[VERSION]
+major 1
+minor 4
+revision 1
+
+[DEFINITIONS]
+AdcRasterTime 1e-07
+BlockDurationRaster 1e-05
+GradientRasterTime 1e-05
+FOV 0.2 0.2
+Name first
+Name second
+
+[VENDOR_STUFF]
+some 1 2 3
+more data
+
+[BLOCKS]
+1 2 0 0 0 1 0 0
+2 10 0 0 0 0 1 0
+
+[TRAP]
+1 1000 10 20 10 0
+
+[ADC]
+1 10 10000 0 0 0
+1 20 10000 0 0 0
+";
+
+#[test]
+fn strict_fails() {
+    assert!(Sequence::from_source(QUIRKY).is_err());
+    let options = LoadOptions::default();
+    assert!(Sequence::from_source_with_options(QUIRKY, &options).is_err());
+}
+
+#[test]
+fn lenient_recovers() {
+    let options = LoadOptions::new().lenient(true);
+    let (seq, warnings) = Sequence::from_source_with_options(QUIRKY, &options).unwrap();
+
+    assert_eq!(seq.blocks.len(), 2);
+    assert_eq!(seq.name.as_deref(), Some("first"));
+    assert_eq!(seq.fov, None);
+    // The first ADC with a duplicated ID wins
+    assert_eq!(seq.blocks[1].adc.as_ref().unwrap().num, 10);
+
+    assert_eq!(warnings.len(), 6, "{warnings:#?}");
+    assert!(matches!(
+        &warnings[0],
+        Warning::UnknownSection { name, line: 14 } if name == "VENDOR_STUFF"
+    ));
+    assert!(matches!(&warnings[1], Warning::DuplicateDefinition(key) if key == "Name"));
+    assert!(matches!(
+        warnings[2],
+        Warning::MissingDefinition(MissingDefinition::RadiofrequencyRasterTime)
+    ));
+    assert!(matches!(&warnings[3], Warning::InvalidDefinition { key, .. } if key == "FOV"));
+    assert!(matches!(
+        warnings[4],
+        Warning::DuplicateId {
+            section: SectionType::Adcs,
+            id: 1,
+            line: Some(27)
+        }
+    ));
+    assert!(matches!(
+        warnings[5],
+        Warning::Validation(ValidationError::EventTooLong { block_id: 1, .. })
+    ));
+}
+
+#[test]
+fn malformed_duplicate() {
+    // The duplicate references a shape that doesn't exist
+    let source = "[VERSION]
+major 1
+minor 4
+revision 1
+
+[DEFINITIONS]
+AdcRasterTime 1e-07
+BlockDurationRaster 1e-05
+GradientRasterTime 1e-05
+RadiofrequencyRasterTime 1e-06
+
+[BLOCKS]
+1 10 0 1 0 0 0 0
+
+[GRADIENTS]
+1 1000 1 0 0
+1 1000 99 0 0
+
+[SHAPES]
+
+shape_id 1
+num_samples 2
+1
+1
+";
+    assert!(Sequence::from_source(source).is_err());
+
+    let options = LoadOptions::new().lenient(true);
+    let (seq, warnings) = Sequence::from_source_with_options(source, &options).unwrap();
+    assert!(seq.blocks[0].gx.is_some());
+    assert!(matches!(
+        warnings[..],
+        [Warning::DuplicateId {
+            section: SectionType::Gradients,
+            id: 1,
+            line: Some(17)
+        }]
+    ));
+}
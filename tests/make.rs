@@ -0,0 +1,326 @@
+use std::{f64::consts::PI, sync::Arc};
+
+use pulseq_rs::{
+    make_adc, make_arbitrary_grad, make_block_pulse, make_extended_trapezoid, make_gauss_pulse,
+    make_sinc_pulse, make_trapezoid, Block, Gradient, MakeError, PulseSpec, RfUse, SequenceBuilder,
+    System, TimeRaster, TrapezoidSpec,
+};
+
+fn assert_close(a: f64, b: f64) {
+    assert!((a - b).abs() <= 1e-9 * b.abs().max(1.0), "{a} != {b}");
+}
+
+fn trap_timing(grad: &Gradient) -> (f64, f64, f64, f64) {
+    match grad {
+        Gradient::Trap {
+            amp,
+            rise,
+            flat,
+            fall,
+            ..
+        } => (*amp, *rise, *flat, *fall),
+        Gradient::Free { .. } => panic!("expected a trapezoid"),
+    }
+}
+
+#[test]
+fn trapezoid() {
+    let system = System::default();
+    let raster = TimeRaster::default();
+
+    // Small areas give a triangle
+    let grad = make_trapezoid(
+        &TrapezoidSpec {
+            area: Some(10.0),
+            ..Default::default()
+        },
+        &system,
+        &raster,
+    )
+    .unwrap();
+    let (_, rise, flat, fall) = trap_timing(&grad);
+    assert_eq!(flat, 0.0);
+    assert_eq!(rise, fall);
+    assert_close(grad.area(raster.grad), 10.0);
+
+    // Large areas are limited by the maximum amplitude
+    let grad = make_trapezoid(
+        &TrapezoidSpec {
+            area: Some(-1000.0),
+            ..Default::default()
+        },
+        &system,
+        &raster,
+    )
+    .unwrap();
+    let (amp, _, flat, _) = trap_timing(&grad);
+    assert!(flat > 0.0 && amp.abs() <= system.max_grad);
+    assert_close(grad.area(raster.grad), -1000.0);
+
+    let grad = make_trapezoid(
+        &TrapezoidSpec {
+            area: Some(200.0),
+            duration: Some(2e-3),
+            ..Default::default()
+        },
+        &system,
+        &raster,
+    )
+    .unwrap();
+    assert_close(grad.duration(raster.grad), 2e-3);
+    assert_close(grad.area(raster.grad), 200.0);
+
+    let grad = make_trapezoid(
+        &TrapezoidSpec {
+            flat_area: Some(64.0),
+            flat_time: Some(1.28e-3),
+            ..Default::default()
+        },
+        &system,
+        &raster,
+    )
+    .unwrap();
+    let (amp, rise, flat, _) = trap_timing(&grad);
+    assert_close(amp, 50_000.0);
+    assert_close(flat, 1.28e-3);
+    assert_close(rise, 10e-6);
+}
+
+#[test]
+fn trapezoid_limits() {
+    let system = System::default();
+    let raster = TimeRaster::default();
+
+    let err = make_trapezoid(
+        &TrapezoidSpec {
+            area: Some(3000.0),
+            duration: Some(1e-3),
+            ..Default::default()
+        },
+        &system,
+        &raster,
+    )
+    .err()
+    .unwrap();
+    assert!(matches!(err, MakeError::AreaTooLarge { .. }));
+
+    let err = make_trapezoid(
+        &TrapezoidSpec {
+            amplitude: Some(2.0 * system.max_grad),
+            flat_time: Some(1e-3),
+            ..Default::default()
+        },
+        &system,
+        &raster,
+    )
+    .err()
+    .unwrap();
+    assert!(matches!(err, MakeError::GradientAmplitude { .. }));
+
+    let err = make_trapezoid(
+        &TrapezoidSpec {
+            amplitude: Some(system.max_grad),
+            flat_time: Some(1e-3),
+            rise_time: Some(10e-6),
+            ..Default::default()
+        },
+        &system,
+        &raster,
+    )
+    .err()
+    .unwrap();
+    assert!(matches!(err, MakeError::SlewRate { .. }));
+
+    let err = make_trapezoid(&TrapezoidSpec::default(), &system, &raster)
+        .err()
+        .unwrap();
+    assert!(matches!(err, MakeError::MissingParameter(_)));
+}
+
+#[test]
+fn sinc_pulse() {
+    let system = System {
+        rf_dead_time: 100e-6,
+        ..Default::default()
+    };
+    let raster = TimeRaster::default();
+
+    let pulse = make_sinc_pulse(
+        PI / 2.0,
+        3e-3,
+        &PulseSpec {
+            slice_thickness: Some(5e-3),
+            apodization: 0.5,
+            usage: RfUse::Excitation,
+            ..Default::default()
+        },
+        &system,
+        &raster,
+    )
+    .unwrap();
+
+    let rf = &pulse.rf;
+    assert_eq!(rf.amp_shape.samples.len(), 3000);
+    assert_eq!(rf.center, Some(1.5e-3));
+    assert_eq!(rf.usage, RfUse::Excitation);
+    let flip: f64 = rf.amp_shape.samples.iter().sum::<f64>() * rf.amp * raster.rf;
+    // The main lobe is positive, the phase shape flips the side lobes
+    let signed: f64 = (rf.amp_shape.samples.iter().zip(&rf.phase_shape.samples))
+        .map(|(m, p)| if *p == 0.0 { *m } else { -m })
+        .sum::<f64>()
+        * rf.amp
+        * raster.rf;
+    assert!(flip > signed);
+    assert_close(signed * 2.0 * PI, PI / 2.0);
+
+    // The pulse plays on the flat top, which has the slice select amplitude
+    let gz = pulse.gz.unwrap();
+    let (amp, rise, flat, _) = trap_timing(&gz);
+    assert_close(amp, 4.0 / 3e-3 / 5e-3);
+    assert_close(flat, 3e-3);
+    assert_close(rf.delay, gz_delay(&gz) + rise);
+    assert!(rf.delay >= system.rf_dead_time);
+
+    // The rephaser undoes the area after the pulse center
+    let rephase = pulse.gz_rephase.unwrap();
+    let after_center = amp * 1.5e-3 + amp * rise / 2.0;
+    assert_close(rephase.area(raster.grad), -after_center);
+}
+
+fn gz_delay(grad: &Gradient) -> f64 {
+    match grad {
+        Gradient::Trap { delay, .. } | Gradient::Free { delay, .. } => *delay,
+    }
+}
+
+#[test]
+fn block_pulse() {
+    let raster = TimeRaster::default();
+    let pulse = make_block_pulse(
+        PI,
+        500e-6,
+        &PulseSpec::default(),
+        &System::default(),
+        &raster,
+    )
+    .unwrap();
+
+    assert!(pulse.gz.is_none() && pulse.gz_rephase.is_none());
+    assert_close(pulse.rf.amp, 1000.0);
+    assert!(pulse.rf.amp_shape.samples.iter().all(|&x| x == 1.0));
+
+    let err = make_block_pulse(
+        PI,
+        500.5e-6,
+        &PulseSpec::default(),
+        &System::default(),
+        &raster,
+    )
+    .err()
+    .unwrap();
+    assert!(matches!(err, MakeError::NotOnRaster { .. }));
+}
+
+#[test]
+fn zero_integral() {
+    // A cosine window over a constant shape has no net area
+    let spec = PulseSpec {
+        time_bw_product: 0.0,
+        apodization: 1.0,
+        ..Default::default()
+    };
+    let err = make_gauss_pulse(
+        PI / 2.0,
+        1e-3,
+        &spec,
+        &System::default(),
+        &TimeRaster::default(),
+    )
+    .err()
+    .unwrap();
+    assert!(matches!(err, MakeError::ZeroIntegral));
+}
+
+#[test]
+fn arbitrary_and_extended() {
+    let system = System::default();
+    let raster = TimeRaster::default();
+
+    let grad = make_arbitrary_grad(&[0.0, 1000.0, 2000.0], 0.0, &system, &raster).unwrap();
+    assert_close(grad.first(), -500.0);
+    assert_close(grad.last(), 2500.0);
+
+    let err = make_arbitrary_grad(&[0.0, system.max_grad], 0.0, &system, &raster)
+        .err()
+        .unwrap();
+    assert!(matches!(err, MakeError::SlewRate { .. }));
+
+    let grad = make_extended_trapezoid(
+        &[0.0, 100e-6, 200e-6],
+        &[1000.0, 100_000.0, 0.0],
+        50e-6,
+        &system,
+        &raster,
+    )
+    .unwrap();
+    assert_close(grad.duration(raster.grad), 250e-6);
+    assert_close(grad.first(), 1000.0);
+    assert_close(grad.last(), 0.0);
+    assert_close(grad.sample(100e-6, raster.grad), 50_500.0);
+    // Sampling on the raster cuts off the tip of the corner
+    let area = 50e-6 * 101_000.0 + 50e-6 * 100_000.0;
+    assert!((grad.area(raster.grad) - area).abs() < 0.01 * area);
+
+    let err = make_extended_trapezoid(&[0.0, 15e-6], &[0.0, 0.0], 0.0, &system, &raster)
+        .err()
+        .unwrap();
+    assert!(matches!(err, MakeError::NotOnRaster { .. }));
+}
+
+#[test]
+fn build_sequence() {
+    let system = System {
+        adc_dead_time: 10e-6,
+        ..Default::default()
+    };
+    let raster = TimeRaster::default();
+
+    let pulse = make_sinc_pulse(
+        PI / 6.0,
+        2e-3,
+        &PulseSpec {
+            slice_thickness: Some(3e-3),
+            ..Default::default()
+        },
+        &system,
+        &raster,
+    )
+    .unwrap();
+    let adc = make_adc(128, 10e-6, 0.0, &system, &raster).unwrap();
+    assert_eq!(adc.delay, 10e-6);
+    assert!(make_adc(128, 10.05e-6, 0.0, &system, &raster).is_err());
+
+    let mut builder = SequenceBuilder::new(raster);
+    builder.add_block(Block {
+        rf: Some(Arc::new(pulse.rf)),
+        gz: pulse.gz.map(Arc::new),
+        ..Default::default()
+    });
+    builder.add_block(Block {
+        gz: pulse.gz_rephase.map(Arc::new),
+        ..Default::default()
+    });
+    // The block has to include the dead time after the ADC
+    builder.add_block(Block {
+        duration: adc.duration() + system.adc_dead_time,
+        adc: Some(Arc::new(adc)),
+        ..Default::default()
+    });
+    let seq = builder.build().unwrap();
+
+    assert!(seq.check_system(&system).is_empty());
+    assert!(seq.check_raster(1e-6).is_empty());
+    // Slice select and rephaser cancel from the pulse center on
+    let kspace = seq.kspace();
+    assert!(kspace.k_adc.iter().all(|k| k[2].abs() < 1e-6));
+}
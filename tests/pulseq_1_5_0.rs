@@ -0,0 +1,201 @@
+use pulseq_rs::{Gradient, RfUse, Sequence};
+
+const SEQ: &str = "
+# Pulseq sequence file
+[VERSION]
+major 1
+minor 5
+revision 0
+
+[DEFINITIONS]
+AdcRasterTime 1e-07
+BlockDurationRaster 1e-05
+GradientRasterTime 1e-05
+RadiofrequencyRasterTime 1e-06
+
+# Format of blocks:
+# NUM DUR RF  GX  GY  GZ  ADC  EXT
+[BLOCKS]
+1 20 1 0 0 1 0 0
+2 10 0 2 0 0 1 1
+3 10 0 0 0 0 0 2
+
+# id ampl. mag_id phase_id time_shape_id center delay freqPPM phasePPM freq phase use
+[RF]
+1 250 1 2 0 50 100 0 0 100 0 e
+
+# id amp first last amp_shape_id time_shape_id delay
+[GRADIENTS]
+2 1000 0 500 3 0 0
+
+# id amplitude rise flat fall delay
+[TRAP]
+1 2000 20 40 20 0
+
+# id num dwell delay freqPPM phasePPM freq phase phase_id
+[ADC]
+1 4 20000 10 0 0 0 0.5 0
+
+[EXTENSIONS]
+1 1 1 0
+2 2 1 0
+extension ROTATIONS 1
+1 1 0 0 0
+extension DELAYS 2
+1 1 100 1 TE
+
+[SHAPES]
+
+shape_id 1
+num_samples 100
+0.01
+0.01
+98
+
+shape_id 2
+num_samples 100
+0
+0
+98
+
+shape_id 3
+num_samples 4
+0.25
+0.5
+0.75
+1
+";
+
+#[test]
+fn inline() {
+    let seq = Sequence::from_source(SEQ).unwrap();
+
+    let rf = seq.blocks[0].rf.as_ref().unwrap();
+    assert_eq!(rf.usage, RfUse::Excitation);
+    assert!((rf.center.unwrap() - 50e-6).abs() < 1e-12);
+
+    match seq.blocks[1].gx.as_deref().unwrap() {
+        Gradient::Free { first, last, .. } => {
+            assert_eq!(*first, Some(0.0));
+            assert_eq!(*last, Some(500.0));
+        }
+        Gradient::Trap { .. } => panic!("expected a free gradient"),
+    }
+
+    let rot = seq.blocks[1].rotation.as_ref().unwrap();
+    assert_eq!(rot.quaternion, [1.0, 0.0, 0.0, 0.0]);
+    let delay = seq.blocks[2].soft_delay.as_ref().unwrap();
+    assert_eq!(delay.hint, "TE");
+    assert!((delay.offset - 100e-6).abs() < 1e-12);
+}
+
+#[test]
+fn columns() {
+    // Every column has a distinct value, so swapped columns are detected
+    let source = "
+[VERSION]
+major 1
+minor 5
+revision 0
+
+[DEFINITIONS]
+AdcRasterTime 1e-07
+BlockDurationRaster 1e-05
+GradientRasterTime 1e-05
+RadiofrequencyRasterTime 1e-06
+
+[BLOCKS]
+1 10 7 8 0 0 9 0
+
+[RF]
+7 250 3 4 5 2 70 1.5 2.5 300 0.75 r
+
+[GRADIENTS]
+8 1000 -100 200 6 12 30
+
+[ADC]
+9 4 2500 40 3.5 4.5 123 0.25 11
+
+[SHAPES]
+
+shape_id 3
+num_samples 2
+1
+1
+
+shape_id 4
+num_samples 2
+0
+0.5
+
+shape_id 5
+num_samples 2
+0
+4
+
+shape_id 6
+num_samples 2
+0.5
+1
+
+shape_id 11
+num_samples 4
+0
+0.1
+0.2
+0.3
+
+shape_id 12
+num_samples 2
+0
+2
+";
+    let seq = Sequence::from_source(source).unwrap();
+    let block = &seq.blocks[0];
+    let close = |a: f64, b: f64| (a - b).abs() < 1e-12;
+
+    let rf = block.rf.as_ref().unwrap();
+    assert_eq!(rf.id, Some(7));
+    assert_eq!(rf.amp, 250.0);
+    assert_eq!(rf.amp_shape.id, Some(3));
+    assert_eq!(rf.phase_shape.id, Some(4));
+    assert_eq!(rf.amp_shape.time_id, Some(5));
+    assert!(close(rf.center.unwrap(), 2e-6));
+    assert!(close(rf.delay, 70e-6));
+    assert_eq!(rf.freq_ppm, 1.5);
+    assert_eq!(rf.phase_ppm, 2.5);
+    assert_eq!(rf.freq, 300.0);
+    assert_eq!(rf.phase, 0.75);
+    assert_eq!(rf.usage, RfUse::Refocusing);
+
+    match block.gx.as_deref().unwrap() {
+        Gradient::Free {
+            id,
+            amp,
+            first,
+            last,
+            shape,
+            delay,
+        } => {
+            assert_eq!(*id, Some(8));
+            assert_eq!(*amp, 1000.0);
+            assert_eq!(*first, Some(-100.0));
+            assert_eq!(*last, Some(200.0));
+            assert_eq!(shape.id, Some(6));
+            assert_eq!(shape.time_id, Some(12));
+            assert!(close(*delay, 30e-6));
+        }
+        Gradient::Trap { .. } => panic!("expected a free gradient"),
+    }
+
+    let adc = block.adc.as_ref().unwrap();
+    assert_eq!(adc.id, Some(9));
+    assert_eq!(adc.num, 4);
+    assert!(close(adc.dwell, 2.5e-6));
+    assert!(close(adc.delay, 40e-6));
+    assert_eq!(adc.freq_ppm, 3.5);
+    assert_eq!(adc.phase_ppm, 4.5);
+    assert_eq!(adc.freq, 123.0);
+    assert_eq!(adc.phase, 0.25);
+    assert_eq!(adc.phase_shape.as_ref().unwrap().id, Some(11));
+}
@@ -0,0 +1,61 @@
+use pulseq_rs::{EventType, Sequence, ValidationError};
+
+#[test]
+fn grappa_acs() {
+    let seq = Sequence::from_file("assets/grappa_acs.seq").unwrap();
+    let errors = seq.check_raster(1e-6);
+    assert!(errors.is_empty(), "{errors:?}");
+}
+
+#[test]
+fn off_raster() {
+    let seq = Sequence::from_source(
+        "
+[VERSION]
+major 1
+minor 3
+revision 1
+
+[BLOCKS]
+1 1 0 1 0 0 1 0
+
+[TRAP]
+1 1000 15 20 10 0
+
+[ADC]
+1 10 10050 0 0 0
+
+[DELAYS]
+1 95
+",
+    )
+    .unwrap();
+
+    let errors = seq.check_raster(1e-6);
+    assert_eq!(errors.len(), 3, "{errors:?}");
+    assert!(matches!(
+        errors[0],
+        ValidationError::BlockNotOnRaster { block_id: 1, .. }
+    ));
+    assert!(matches!(
+        errors[1],
+        ValidationError::EventNotOnRaster {
+            ty: EventType::Gx,
+            block_id: 1,
+            timing: "rise",
+            ..
+        }
+    ));
+    assert!(matches!(
+        errors[2],
+        ValidationError::EventNotOnRaster {
+            ty: EventType::Adc,
+            block_id: 1,
+            timing: "dwell",
+            ..
+        }
+    ));
+
+    // A tolerance of half a raster step accepts everything
+    assert!(seq.check_raster(0.5).is_empty());
+}
@@ -0,0 +1,99 @@
+use std::f64::consts::PI;
+
+use pulseq_rs::{
+    make_block_pulse, make_gauss_pulse, make_sinc_pulse, PulseSpec, System, TimeRaster,
+};
+
+fn assert_close(a: f64, b: f64, tol: f64) {
+    assert!((a - b).abs() <= tol * b.abs(), "{a} != {b}");
+}
+
+#[test]
+fn block_pulse() {
+    let raster = TimeRaster::default();
+    let rf = make_block_pulse(
+        PI / 2.0,
+        1e-3,
+        &PulseSpec::default(),
+        &System::default(),
+        &raster,
+    )
+    .unwrap()
+    .rf;
+
+    // 90° in 1 ms need a B1 of 250 Hz
+    assert_close(rf.flip_angle(raster.rf), PI / 2.0, 1e-9);
+    assert_close(rf.peak_b1(), 250.0, 1e-9);
+    assert_close(rf.energy(raster.rf), 250.0 * 250.0 * 1e-3, 1e-9);
+    assert_close(rf.isodelay(raster.rf), 0.5e-3, 1e-9);
+    // The spectrum is a sinc with a FWHM of 1.207 / duration
+    assert_close(rf.bandwidth(raster.rf), 1207.0, 1e-2);
+}
+
+#[test]
+fn sinc_pulse() {
+    let system = System::default();
+    let raster = TimeRaster::default();
+    let spec = PulseSpec {
+        time_bw_product: 4.0,
+        center_pos: 0.3,
+        ..Default::default()
+    };
+    let rf = make_sinc_pulse(PI / 6.0, 2e-3, &spec, &system, &raster)
+        .unwrap()
+        .rf;
+
+    assert_close(rf.flip_angle(raster.rf), PI / 6.0, 1e-6);
+    assert_close(rf.peak_time(raster.rf), 0.6e-3, 1e-3);
+    assert_close(rf.isodelay(raster.rf), 1.4e-3, 1e-3);
+    assert_close(rf.bandwidth(raster.rf), 2000.0, 5e-2);
+    assert_close(rf.time_bandwidth_product(raster.rf), 4.0, 5e-2);
+    // A block pulse of the same duration and flip angle needs less energy
+    let area = rf.flip_angle(raster.rf) / (2.0 * PI);
+    assert!(rf.energy(raster.rf) > area * area / 2e-3);
+
+    // Doubling the flip angle quadruples the energy
+    let double = make_sinc_pulse(PI / 3.0, 2e-3, &spec, &system, &raster)
+        .unwrap()
+        .rf;
+    assert_close(double.energy(raster.rf), 4.0 * rf.energy(raster.rf), 1e-9);
+}
+
+#[test]
+fn gauss_pulse() {
+    let raster = TimeRaster::default();
+    let rf = make_gauss_pulse(
+        PI / 2.0,
+        4e-3,
+        &PulseSpec::default(),
+        &System::default(),
+        &raster,
+    )
+    .unwrap()
+    .rf;
+    // The spectrum is a gaussian as well, with a FWHM of 2 sqrt(ln 2 / pi)
+    // times the time bandwidth product / duration
+    assert_close(rf.flip_angle(raster.rf), PI / 2.0, 1e-6);
+    let fwhm = 2.0 * (2f64.ln() / PI).sqrt() * 1000.0;
+    assert_close(rf.bandwidth(raster.rf), fwhm, 1e-3);
+}
+
+#[test]
+fn zero_pulse() {
+    let raster = TimeRaster::default();
+    let mut rf = make_block_pulse(
+        PI / 2.0,
+        1e-3,
+        &PulseSpec::default(),
+        &System::default(),
+        &raster,
+    )
+    .unwrap()
+    .rf;
+    rf.amp = 0.0;
+
+    assert_eq!(rf.flip_angle(raster.rf), 0.0);
+    assert_eq!(rf.energy(raster.rf), 0.0);
+    assert_eq!(rf.bandwidth(raster.rf), 0.0);
+    assert_eq!(rf.time_bandwidth_product(raster.rf), 0.0);
+}
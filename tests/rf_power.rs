@@ -0,0 +1,62 @@
+use std::{f64::consts::PI, sync::Arc};
+
+use pulseq_rs::{
+    make_block_pulse, Block, PulseSpec, Sequence, SequenceBuilder, System, TimeRaster, GAMMA,
+};
+
+fn assert_close(a: f64, b: f64) {
+    assert!((a - b).abs() <= 1e-9 * b.abs(), "{a} != {b}");
+}
+
+/// 180° block pulses of 1 ms (500 Hz) every `tr` for `active`, followed by
+/// a delay until `duration`
+fn pulses(tr: f64, active: f64, duration: f64) -> Sequence {
+    let raster = TimeRaster::default();
+    let rf = make_block_pulse(PI, 1e-3, &PulseSpec::default(), &System::default(), &raster)
+        .unwrap()
+        .rf;
+    let rf = Arc::new(rf);
+
+    let mut builder = SequenceBuilder::new(raster);
+    let count = (active / tr).round() as usize;
+    for _ in 0..count {
+        builder.add_block(Block {
+            rf: Some(rf.clone()),
+            duration: tr,
+            ..Default::default()
+        });
+    }
+    if duration > active {
+        builder.add_delay(duration - active);
+    }
+    builder.build().unwrap()
+}
+
+#[test]
+fn energy_and_peak() {
+    // Scaling of 1 W/T²: power is |B1|² in T²
+    let b1 = 500.0 / GAMMA;
+    let power = pulses(0.1, 5.0, 5.0).rf_power(1.0);
+    assert_close(power.energy, 50.0 * b1 * b1 * 1e-3);
+    assert_close(power.peak_power, b1 * b1);
+
+    // Power scales linearly
+    let scaled = pulses(0.1, 5.0, 5.0).rf_power(3.5);
+    assert_close(scaled.energy, 3.5 * power.energy);
+    assert_close(scaled.peak_power, 3.5 * power.peak_power);
+
+    // Shorter than both windows: the sequence is assumed to repeat
+    assert_close(power.average_10s, power.energy / 5.0);
+    assert_close(power.average_6min, power.energy / 5.0);
+}
+
+#[test]
+fn sliding_windows() {
+    // 20 s of pulses, then 40 s of silence
+    let power = pulses(0.1, 20.0, 60.0).rf_power(1.0);
+    let energy = power.energy;
+    // 100 pulses fit into any 10 s window within the first 20 s
+    assert_close(power.average_10s, energy / 2.0 / 10.0);
+    assert_close(power.average_6min, energy / 60.0);
+    assert!(power.average_10s > power.average_6min);
+}
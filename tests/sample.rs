@@ -0,0 +1,103 @@
+use std::f64::consts::TAU;
+
+use pulseq_rs::{Rf, Sample, Sequence};
+
+const SEQ: &str = "
+[VERSION]
+major 1
+minor 4
+revision 1
+
+[DEFINITIONS]
+AdcRasterTime 1e-07
+BlockDurationRaster 1e-05
+GradientRasterTime 1e-05
+RadiofrequencyRasterTime 1e-06
+
+[BLOCKS]
+1 10 1 1 0 0 0 0
+2 20 0 0 2 0 1 0
+
+[RF]
+1 250 1 2 0 10 100 0.5
+
+[GRADIENTS]
+2 1000 3 0 0
+
+[TRAP]
+1 2000 20 40 20 10
+
+[ADC]
+1 10 5000 50 0 0
+
+[SHAPES]
+
+shape_id 1
+num_samples 4
+1
+1
+1
+1
+
+shape_id 2
+num_samples 4
+0
+0
+0.25
+0.25
+
+shape_id 3
+num_samples 4
+0
+0.5
+1
+1
+";
+
+fn assert_close(a: f64, b: f64) {
+    assert!((a - b).abs() < 1e-9, "{a} != {b}");
+}
+
+#[test]
+fn channels() {
+    let seq = Sequence::from_source(SEQ).unwrap();
+    let times = [5e-6, 12.5e-6, 40e-6, 85e-6, 115e-6, 160e-6, 300e-6, -1.0];
+    let samples = seq.sample_many(&times);
+
+    // Before the RF and trap delays
+    assert_eq!(samples[0], Sample::default());
+
+    // RF with phase shape and frequency offset, rising trap
+    assert_close(samples[1].rf_amp, 250.0);
+    assert_close(samples[1].rf_phase, 0.5 + TAU * 0.25 + TAU * 100.0 * 2.5e-6);
+    assert_close(samples[1].gx, 250.0);
+
+    // Flat top and ramp down
+    assert_eq!(samples[2].rf_amp, 0.0);
+    assert_close(samples[2].gx, 2000.0);
+    assert_close(samples[3].gx, 500.0);
+
+    // Free gradient in the second block, interpolated between samples
+    assert_close(samples[4].gy, 500.0);
+    assert!(!samples[4].adc);
+    assert_eq!(samples[5].gy, 0.0);
+    assert!(samples[5].adc);
+
+    // Outside of the sequence
+    assert_eq!(samples[6], Sample::default());
+    assert_eq!(samples[7], Sample::default());
+    assert_eq!(seq.sample(115e-6), samples[4]);
+}
+
+#[test]
+fn rf_raster_edges() {
+    let rf = Rf::from_real(&[100.0, 200.0, 300.0, 400.0], 10e-6);
+
+    // Times on the raster select the sample starting there, despite
+    // rounding errors like (12e-6 - 10e-6) / 1e-6 < 2
+    for (k, t) in [10e-6, 11e-6, 12e-6, 13e-6].into_iter().enumerate() {
+        let (amp, _) = rf.sample(t, 1e-6).unwrap();
+        assert_close(amp, 100.0 * (k + 1) as f64);
+    }
+    assert_eq!(rf.sample(14e-6, 1e-6), None);
+}
@@ -0,0 +1,88 @@
+#![cfg(feature = "serde")]
+use std::sync::Arc;
+
+use pulseq_rs::Sequence;
+
+fn round_trip(seq: &Sequence) -> Sequence {
+    let json = serde_json::to_string(seq).unwrap();
+    let reloaded: Sequence = serde_json::from_str(&json).unwrap();
+    assert_eq!(json, serde_json::to_string(&reloaded).unwrap());
+    reloaded
+}
+
+#[test]
+fn grappa_acs() {
+    let seq = Sequence::from_file("assets/grappa_acs.seq").unwrap();
+    let reloaded = round_trip(&seq);
+
+    // Serialization is lossless, so the written files are identical
+    assert_eq!(seq.to_source(), reloaded.to_source());
+    assert_eq!(
+        seq.signature.as_ref().unwrap().hash,
+        reloaded.signature.as_ref().unwrap().hash
+    );
+}
+
+#[test]
+fn shared_events() {
+    let source = "
+[VERSION]
+major 1
+minor 4
+revision 1
+
+[DEFINITIONS]
+AdcRasterTime 1e-07
+BlockDurationRaster 1e-05
+GradientRasterTime 1e-05
+RadiofrequencyRasterTime 1e-06
+
+[BLOCKS]
+1 10 7 0 0 0 0 1
+2 10 7 0 0 0 0 1
+
+[RF]
+7 250 4 4 0 0 0 0
+
+[EXTENSIONS]
+1 1 1 0
+extension TRIGGERS 1
+1 2 1 10 20
+
+[SHAPES]
+
+shape_id 4
+num_samples 2
+1
+1
+";
+    let seq = Sequence::from_source(source).unwrap();
+    let json = serde_json::to_value(&seq).unwrap();
+    // Events and shapes are written once and referenced by their ID
+    assert_eq!(json["rfs"].as_object().unwrap().len(), 1);
+    assert_eq!(json["rfs"]["7"]["amp_shape"], 4);
+    assert_eq!(json["shapes"].as_object().unwrap().len(), 1);
+    assert_eq!(json["blocks"][1]["rf"], 7);
+    assert_eq!(json["triggers"].as_object().unwrap().len(), 1);
+
+    let reloaded = round_trip(&seq);
+    let [a, b] = &reloaded.blocks[..] else {
+        panic!("expected two blocks");
+    };
+    let rf = a.rf.as_ref().unwrap();
+    assert!(Arc::ptr_eq(rf, b.rf.as_ref().unwrap()));
+    assert!(Arc::ptr_eq(&rf.amp_shape, &rf.phase_shape));
+    assert!(Arc::ptr_eq(&a.triggers[0], &b.triggers[0]));
+    assert_eq!(rf.id, Some(7));
+    assert!((a.triggers[0].delay - 10e-6).abs() < 1e-12);
+}
+
+#[test]
+fn missing_reference() {
+    let seq = Sequence::from_file("assets/grappa_acs.seq").unwrap();
+    let mut json = serde_json::to_value(&seq).unwrap();
+    json["shapes"] = serde_json::json!({});
+
+    let err = serde_json::from_value::<Sequence>(json).unwrap_err();
+    assert!(err.to_string().contains("missing shape"));
+}
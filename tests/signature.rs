@@ -0,0 +1,36 @@
+use pulseq_rs::{Error, Sequence};
+
+#[test]
+fn valid() {
+    let seq = Sequence::from_file_strict("assets/grappa_acs.seq").unwrap();
+    let signature = seq.signature.unwrap();
+    assert_eq!(signature.typ, "md5");
+    assert_eq!(signature.hash, "6921c5c4e89d8664ef1f77c55dfc1c76");
+    assert_eq!(signature.is_valid(), Some(true));
+}
+
+#[test]
+fn tampered() {
+    let source = std::fs::read_to_string("assets/grappa_acs.seq").unwrap();
+    let source = source.replacen("Name grappa_acs", "Name tampered", 1);
+
+    let seq = Sequence::from_source(&source).unwrap();
+    assert_eq!(seq.signature.unwrap().is_valid(), Some(false));
+    assert!(matches!(
+        Sequence::from_source_strict(&source),
+        Err(Error::SignatureError(_))
+    ));
+}
+
+#[test]
+fn written() {
+    let seq = Sequence::from_file("assets/grappa_acs.seq").unwrap();
+    let source = seq.to_source();
+    let reloaded = Sequence::from_source_strict(&source).unwrap();
+    assert_eq!(reloaded.signature.unwrap().is_valid(), Some(true));
+
+    // Unsigned files are fine, unless loaded strictly
+    let unsigned = &source[..source.find("\n[SIGNATURE]").unwrap()];
+    assert!(Sequence::from_source(unsigned).unwrap().signature.is_none());
+    assert!(Sequence::from_source_strict(unsigned).is_err());
+}
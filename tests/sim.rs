@@ -0,0 +1,266 @@
+#![cfg(feature = "sim")]
+use std::{
+    f64::consts::{PI, TAU},
+    sync::Arc,
+};
+
+use pulseq_rs::{
+    make_adc, make_block_pulse, make_sinc_pulse, make_trapezoid,
+    sim::{bloch, epg, slice_profile, Isochromat},
+    Block, Gradient, PulseSpec, Sequence, SequenceBuilder, System, TimeRaster, TrapezoidSpec,
+};
+
+fn assert_close(a: (f64, f64), b: (f64, f64), tol: f64) {
+    assert!(
+        (a.0 - b.0).abs() < tol && (a.1 - b.1).abs() < tol,
+        "{a:?} != {b:?}"
+    );
+}
+
+/// Block pulse with the given flip angle, followed by an ADC
+fn fid(flip_angle: f64, phase: f64) -> Sequence {
+    let system = System::default();
+    let raster = TimeRaster::default();
+    let mut pulse =
+        make_block_pulse(flip_angle, 100e-6, &PulseSpec::default(), &system, &raster).unwrap();
+    pulse.rf.phase = phase;
+    let adc = make_adc(100, 10e-6, 0.0, &system, &raster).unwrap();
+
+    let mut builder = SequenceBuilder::new(raster);
+    builder.add_block(Block {
+        rf: Some(Arc::new(pulse.rf)),
+        ..Default::default()
+    });
+    builder.add_block(Block {
+        adc: Some(Arc::new(adc)),
+        ..Default::default()
+    });
+    builder.build().unwrap()
+}
+
+#[test]
+fn free_induction_decay() {
+    let seq = fid(PI / 2.0, 0.0);
+
+    // A 90° pulse with phase 0 rotates the magnetization to -y
+    let signal = bloch(&seq, &[Isochromat::new(f64::INFINITY, f64::INFINITY)]);
+    assert_eq!(signal.len(), 100);
+    for s in &signal {
+        assert_close(*s, (0.0, -1.0), 1e-9);
+    }
+
+    // The ADC phase demodulates the RF phase
+    let mut seq = fid(PI / 2.0, 0.5);
+    let adc = seq.blocks[1].adc.as_mut().unwrap();
+    Arc::get_mut(adc).unwrap().phase = 0.5;
+    let signal = bloch(&seq, &[Isochromat::new(f64::INFINITY, f64::INFINITY)]);
+    assert_close(signal[0], (0.0, -1.0), 1e-9);
+
+    // T2 decay and off-resonance precession between the samples
+    let iso = Isochromat {
+        off_resonance: 100.0,
+        m0: 2.0,
+        ..Isochromat::new(1.0, 0.05)
+    };
+    let signal = bloch(&fid(PI / 2.0, 0.0), &[iso]);
+    let (re, im) = signal[0];
+    for (i, s) in signal.iter().enumerate() {
+        let t = i as f64 * 10e-6;
+        let decay = (-t / 0.05).exp();
+        let (sin, cos) = (TAU * 100.0 * t).sin_cos();
+        let expected = (decay * (re * cos - im * sin), decay * (re * sin + im * cos));
+        assert_close(*s, expected, 1e-9);
+    }
+    assert!((re.hypot(im) - 2.0).abs() < 1e-2);
+}
+
+#[test]
+fn inversion() {
+    // A 180° pulse leaves no transverse magnetization
+    let signal = bloch(
+        &fid(PI, 0.0),
+        &[Isochromat::new(f64::INFINITY, f64::INFINITY)],
+    );
+    assert!(signal.iter().all(|s| s.0.hypot(s.1) < 1e-9));
+}
+
+#[test]
+fn gradient_echo() {
+    let system = System::default();
+    let raster = TimeRaster::default();
+    let pulse =
+        make_block_pulse(PI / 2.0, 100e-6, &PulseSpec::default(), &system, &raster).unwrap();
+    let readout = make_trapezoid(
+        &TrapezoidSpec {
+            flat_area: Some(1000.0),
+            flat_time: Some(1e-3),
+            ..Default::default()
+        },
+        &system,
+        &raster,
+    )
+    .unwrap();
+    let prephaser = make_trapezoid(
+        &TrapezoidSpec {
+            area: Some(-readout.area(raster.grad) / 2.0),
+            ..Default::default()
+        },
+        &system,
+        &raster,
+    )
+    .unwrap();
+    let rise = readout.duration(raster.grad) - 1e-3;
+    let adc = make_adc(101, 1e-3 / 101.0, rise / 2.0, &system, &raster);
+    // Dwell times must be on the ADC raster
+    assert!(adc.is_err());
+    let adc = make_adc(100, 10e-6, rise / 2.0, &system, &raster).unwrap();
+
+    let mut builder = SequenceBuilder::new(raster);
+    builder.add_block(Block {
+        rf: Some(Arc::new(pulse.rf)),
+        ..Default::default()
+    });
+    builder.add_block(Block {
+        gx: Some(Arc::new(prephaser)),
+        ..Default::default()
+    });
+    builder.add_block(Block {
+        gx: Some(Arc::new(readout)),
+        adc: Some(Arc::new(adc)),
+        ..Default::default()
+    });
+    let seq = builder.build().unwrap();
+
+    // Off-center isochromats have the phase 2 pi k x
+    let kspace = seq.kspace();
+    let x = 0.3e-3;
+    let iso = Isochromat {
+        position: [x, 0.0, 0.0],
+        ..Isochromat::new(f64::INFINITY, f64::INFINITY)
+    };
+    let signal = bloch(&seq, &[iso]);
+    for (s, k) in signal.iter().zip(&kspace.k_adc) {
+        let phase = TAU * k[0] * x - PI / 2.0;
+        assert_close(*s, (phase.cos(), phase.sin()), 1e-6);
+    }
+}
+
+/// CPMG echo train: 90° pulse, then refocusing pulses (phase 90°) between
+/// crushers of area 100 1/m, with an ADC centered on each spin echo
+fn cpmg(refocusing: f64, echoes: usize) -> Sequence {
+    let system = System::default();
+    let raster = TimeRaster::default();
+    let pulse = |flip: f64, phase: f64| {
+        let mut rf = make_block_pulse(flip, 100e-6, &PulseSpec::default(), &system, &raster)
+            .unwrap()
+            .rf;
+        rf.phase = phase;
+        Arc::new(rf)
+    };
+    let crusher = make_trapezoid(
+        &TrapezoidSpec {
+            area: Some(100.0),
+            duration: Some(1e-3),
+            ..Default::default()
+        },
+        &system,
+        &raster,
+    )
+    .unwrap();
+    let crusher = Arc::new(crusher);
+    let adc = Arc::new(make_adc(100, 10e-6, 0.0, &system, &raster).unwrap());
+
+    let mut builder = SequenceBuilder::new(raster.clone());
+    builder.add_block(Block {
+        rf: Some(pulse(PI / 2.0, 0.0)),
+        ..Default::default()
+    });
+    // Echo spacing of 3.1 ms: refocusing centers are 1.55 ms from the
+    // excitation center and from the ADC centers
+    builder.add_delay(0.45e-3);
+    let refocus = pulse(refocusing, PI / 2.0);
+    for _ in 0..echoes {
+        builder.add_block(Block {
+            gz: Some(crusher.clone()),
+            ..Default::default()
+        });
+        builder.add_block(Block {
+            rf: Some(refocus.clone()),
+            ..Default::default()
+        });
+        builder.add_block(Block {
+            gz: Some(crusher.clone()),
+            ..Default::default()
+        });
+        builder.add_block(Block {
+            adc: Some(adc.clone()),
+            ..Default::default()
+        });
+    }
+    builder.build().unwrap()
+}
+
+#[test]
+fn epg_echo_train() {
+    // Perfect refocusing: pure T2 decay
+    let tissue = Isochromat::new(1.0, 0.05);
+    let signal = epg(&cpmg(PI, 5), &tissue, 100.0);
+    assert_eq!(signal.len(), 5);
+    for (n, s) in signal.iter().enumerate() {
+        let t = (n + 1) as f64 * 3.1e-3;
+        assert_close(*s, (0.0, -(-t / 0.05).exp()), 1e-9);
+    }
+
+    // With 120° refocusing, the first echo is sin²(60°) and stimulated
+    // echoes increase the second one
+    let tissue = Isochromat::new(f64::INFINITY, f64::INFINITY);
+    let signal = epg(&cpmg(2.0 * PI / 3.0, 2), &tissue, 100.0);
+    assert_close(signal[0], (0.0, -0.75), 1e-9);
+    assert!(signal[1].1 < -0.75);
+}
+
+#[test]
+fn epg_fid() {
+    // Without gradients, EPG and Bloch simulation agree
+    let seq = fid(PI / 3.0, 0.2);
+    let tissue = Isochromat::new(0.5, 0.02);
+    let echo = epg(&seq, &tissue, 1.0)[0];
+    let samples = bloch(&seq, &[tissue]);
+    // The ADC center lies between sample 49 and 50
+    let center = (
+        0.5 * (samples[49].0 + samples[50].0),
+        0.5 * (samples[49].1 + samples[50].1),
+    );
+    assert_close(echo, center, 1e-4);
+}
+
+#[test]
+fn sinc_slice_profile() {
+    let system = System::default();
+    let raster = TimeRaster::default();
+    let spec = PulseSpec {
+        apodization: 0.5,
+        slice_thickness: Some(5e-3),
+        ..Default::default()
+    };
+    let mut pulse = make_sinc_pulse(PI / 6.0, 3e-3, &spec, &system, &raster).unwrap();
+    let gz = pulse.gz.unwrap();
+    // Shift the slice by 10 mm
+    let Gradient::Trap { amp, .. } = gz else {
+        panic!("expected a trapezoid");
+    };
+    pulse.rf.freq = amp * 10e-3;
+    let block = Block {
+        rf: Some(Arc::new(pulse.rf)),
+        gz: Some(Arc::new(gz)),
+        ..Default::default()
+    };
+
+    let positions: Vec<f64> = (-200..=200).map(|i| 10e-3 + i as f64 * 0.05e-3).collect();
+    let profile = slice_profile(&block, &raster, &positions).unwrap();
+    assert!((profile.center() - 10e-3).abs() < 0.05e-3);
+    assert!((profile.thickness() - 5e-3).abs() < 0.5e-3);
+    // Small tip angle: sin(30°) at the slice center
+    let max = profile.transverse().into_iter().fold(0.0, f64::max);
+    assert!((max - 0.5).abs() < 1e-2);
+}
@@ -0,0 +1,124 @@
+use pulseq_rs::{EventType, Sequence, System, ValidationError, GAMMA};
+
+const SEQ: &str = "
+[VERSION]
+major 1
+minor 4
+revision 1
+
+[DEFINITIONS]
+AdcRasterTime 1e-07
+BlockDurationRaster 1e-05
+GradientRasterTime 1e-05
+RadiofrequencyRasterTime 1e-06
+
+[BLOCKS]
+1 40 1 1 0 0 0 0
+2 20 0 0 2 0 1 0
+
+[RF]
+1 250 1 1 0 10 0 0
+
+[GRADIENTS]
+2 2000000 2 0 0
+
+[TRAP]
+1 2000000 100 200 100 0
+
+[ADC]
+1 10 10000 0 0 0
+
+[SHAPES]
+
+shape_id 1
+num_samples 4
+1
+1
+1
+1
+
+shape_id 2
+num_samples 4
+0
+1
+1
+0
+";
+
+#[test]
+fn grappa_acs() {
+    let seq = Sequence::from_file("assets/grappa_acs.seq").unwrap();
+    // Uses up to 45 mT/m and 200 T/m/s, which is too much for the defaults
+    assert!(!seq.check_system(&System::default()).is_empty());
+
+    let system = System {
+        max_grad: 45e-3 * GAMMA,
+        max_slew: 200.0 * GAMMA,
+        ..Default::default()
+    };
+    let errors = seq.check_system(&system);
+    assert!(errors.is_empty(), "{errors:?}");
+}
+
+#[test]
+fn violations() {
+    let seq = Sequence::from_source(SEQ).unwrap();
+    let system = System {
+        max_grad: 40e-3 * GAMMA,
+        max_slew: 100.0 * GAMMA,
+        rf_ringdown_time: 390e-6,
+        rf_dead_time: 20e-6,
+        adc_dead_time: 10e-6,
+        b0: 3.0,
+    };
+    let errors = seq.check_system(&system);
+
+    // 2 MHz/m with 100 us ramps: 47 mT/m and 470 T/m/s
+    assert!(matches!(
+        errors[0],
+        ValidationError::GradientAmplitude {
+            ty: EventType::Gx,
+            block_id: 1,
+            ..
+        }
+    ));
+    assert!(matches!(
+        errors[1],
+        ValidationError::SlewRate {
+            ty: EventType::Gx,
+            block_id: 1,
+            ..
+        }
+    ));
+    assert!(matches!(
+        errors[2],
+        ValidationError::RfDeadTime { block_id: 1, .. }
+    ));
+    assert!(matches!(
+        errors[3],
+        ValidationError::RfRingdownTime { block_id: 1, .. }
+    ));
+
+    // The free gradient ramps up in 10 us: slew rate of 200 GHz/m/s
+    assert!(matches!(
+        errors[4],
+        ValidationError::GradientAmplitude {
+            ty: EventType::Gy,
+            block_id: 2,
+            ..
+        }
+    ));
+    assert!(matches!(
+        errors[5],
+        ValidationError::SlewRate {
+            ty: EventType::Gy,
+            block_id: 2,
+            ..
+        }
+    ));
+    assert!(matches!(
+        errors[6],
+        ValidationError::AdcDeadTime { block_id: 2, .. }
+    ));
+    assert_eq!(errors.len(), 7);
+}
@@ -0,0 +1,63 @@
+use pulseq_rs::{Event, Sequence};
+
+fn delays(count: usize) -> Sequence {
+    let mut source = String::from(
+        "[VERSION]
+major 1
+minor 4
+revision 1
+
+[DEFINITIONS]
+AdcRasterTime 1e-07
+BlockDurationRaster 1e-05
+GradientRasterTime 1e-05
+RadiofrequencyRasterTime 1e-06
+
+[BLOCKS]
+",
+    );
+    for i in 1..=count {
+        source += &format!("{i} 1 0 0 0 0 0 0\n");
+    }
+    Sequence::from_source(&source).unwrap()
+}
+
+#[test]
+fn no_drift() {
+    let count = 100_000;
+    let seq = delays(count);
+    let timeline = seq.timeline();
+
+    // Summing up 10 us this often would not exactly add up to 1 s
+    assert_eq!(timeline.block_count(), count);
+    assert_eq!(timeline.duration(), 1.0);
+    assert_eq!(timeline.block_start(count / 2), 0.5);
+    assert_eq!(timeline.block_end(count - 1), 1.0);
+
+    assert_eq!(timeline.block_at(-1e-9), None);
+    assert_eq!(timeline.block_at(0.0), Some(0));
+    assert_eq!(timeline.block_at(0.5), Some(count / 2));
+    assert_eq!(timeline.block_at(0.5 - 1e-9), Some(count / 2 - 1));
+    assert_eq!(timeline.block_at(1.0), None);
+}
+
+#[test]
+fn events() {
+    let seq = Sequence::from_file("assets/grappa_acs.seq").unwrap();
+    let timeline = seq.timeline();
+    let events: Vec<_> = seq.events().collect();
+
+    let adc_count = seq.blocks.iter().filter(|b| b.adc.is_some()).count();
+    let adcs: Vec<_> = events
+        .iter()
+        .filter(|e| matches!(e.event, Event::Adc(_)))
+        .collect();
+    assert_eq!(adcs.len(), adc_count);
+
+    for event in &events {
+        assert!(event.start <= event.end);
+        assert!(event.start >= timeline.block_start(event.block_index));
+        assert!(event.end <= timeline.block_end(event.block_index) + 1e-12);
+        assert_eq!(timeline.block_at(event.start), Some(event.block_index));
+    }
+}
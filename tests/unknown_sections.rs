@@ -0,0 +1,56 @@
+use pulseq_rs::{Sequence, UnknownSection};
+
+const SOURCE: &str = "[VERSION]
+major 1
+minor 4
+revision 1
+
+[DEFINITIONS]
+AdcRasterTime 1e-07
+BlockDurationRaster 1e-05
+GradientRasterTime 1e-05
+RadiofrequencyRasterTime 1e-06
+
+[VENDOR_HINTS]
+# Comments are dropped
+fast_mode 1
+  coil  head 20
+
+[BLOCKS]
+1 10 0 0 0 0 0 0
+
+[EMPTY]
+";
+
+#[test]
+fn captured() {
+    let seq = Sequence::from_source(SOURCE).unwrap();
+    assert_eq!(seq.blocks.len(), 1);
+    assert_eq!(
+        seq.unknown_sections,
+        [
+            UnknownSection {
+                name: "VENDOR_HINTS".to_owned(),
+                lines: vec!["fast_mode 1".to_owned(), "coil  head 20".to_owned()],
+            },
+            UnknownSection {
+                name: "EMPTY".to_owned(),
+                lines: vec![],
+            },
+        ]
+    );
+}
+
+#[test]
+fn written_back() {
+    let seq = Sequence::from_source(SOURCE).unwrap();
+    let reloaded = Sequence::from_source(&seq.to_source()).unwrap();
+    assert_eq!(seq.unknown_sections, reloaded.unknown_sections);
+}
+
+#[test]
+fn broken_known_section() {
+    // Known sections are not captured as unknown if they fail to parse
+    let source = SOURCE.replace("1 10 0 0 0 0 0 0", "1 10 0 0 0");
+    assert!(Sequence::from_source(&source).is_err());
+}
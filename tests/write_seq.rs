@@ -0,0 +1,242 @@
+use std::sync::Arc;
+
+use pulseq_rs::{
+    make_extended_trapezoid, Adc, Block, Rf, Sequence, SequenceBuilder, System, TimeRaster,
+};
+
+/// Writing, reading and writing again must result in the identical file
+fn round_trip(seq: &Sequence) -> Sequence {
+    let source = seq.to_source();
+    let reloaded = Sequence::from_source(&source).unwrap();
+    assert_eq!(source, reloaded.to_source());
+    reloaded
+}
+
+#[test]
+fn grappa_acs() {
+    let seq = Sequence::from_file("assets/grappa_acs.seq").unwrap();
+    let reloaded = round_trip(&seq);
+
+    assert_eq!(seq.name, reloaded.name);
+    assert_eq!(seq.fov, reloaded.fov);
+    assert_eq!(seq.definitions, reloaded.definitions);
+    assert_eq!(seq.blocks.len(), reloaded.blocks.len());
+    for (a, b) in seq.blocks.iter().zip(&reloaded.blocks) {
+        assert_eq!(a.id, b.id);
+        assert!((a.duration - b.duration).abs() < 1e-9);
+        assert_eq!(a.rf.is_some(), b.rf.is_some());
+        assert_eq!(a.adc.is_some(), b.adc.is_some());
+        if let (Some(a), Some(b)) = (&a.rf, &b.rf) {
+            assert_eq!(a.amp, b.amp);
+            assert_eq!(a.phase, b.phase);
+            for (x, y) in a.amp_shape.samples.iter().zip(&b.amp_shape.samples) {
+                assert!((x - y).abs() < 1e-7);
+            }
+        }
+    }
+}
+
+#[test]
+fn extensions() {
+    let source = "
+[VERSION]
+major 1
+minor 4
+revision 1
+
+[DEFINITIONS]
+AdcRasterTime 1e-07
+BlockDurationRaster 1e-05
+GradientRasterTime 1e-05
+RadiofrequencyRasterTime 1e-06
+
+[BLOCKS]
+1 10 0 0 0 0 0 1
+2 10 0 0 0 0 0 3
+3 10 0 0 0 0 0 3
+
+[EXTENSIONS]
+1 1 1 2
+2 2 1 0
+3 3 1 2
+extension LABELSET 1
+1 0 LIN
+extension TRIGGERS 2
+1 2 1 10 20
+extension CUSTOM 3
+1 some data
+";
+    let seq = Sequence::from_source(source).unwrap();
+    let reloaded = round_trip(&seq);
+
+    for (a, b) in seq.blocks.iter().zip(&reloaded.blocks) {
+        assert_eq!(a.labels, b.labels);
+        assert_eq!(a.triggers.len(), b.triggers.len());
+        assert_eq!(a.extensions.len(), b.extensions.len());
+    }
+    assert_eq!(reloaded.blocks[2].extensions[0].data, "some data");
+}
+
+#[test]
+fn keeps_ids() {
+    let source = "
+[VERSION]
+major 1
+minor 4
+revision 1
+
+[DEFINITIONS]
+AdcRasterTime 1e-07
+BlockDurationRaster 1e-05
+GradientRasterTime 1e-05
+RadiofrequencyRasterTime 1e-06
+
+[BLOCKS]
+1 10 7 0 5 9 3 0
+
+[RF]
+7 250 4 6 0 0 0 0
+
+[GRADIENTS]
+9 1000 6 0 0
+
+[TRAP]
+5 1000 10 20 10 0
+
+[ADC]
+3 10 10000 0 0 0
+
+[SHAPES]
+
+shape_id 4
+num_samples 2
+1
+1
+
+shape_id 6
+num_samples 2
+0
+0
+";
+    let seq = Sequence::from_source(source).unwrap();
+    let block = &seq.blocks[0];
+    let rf = block.rf.as_ref().unwrap();
+    assert_eq!(rf.id, Some(7));
+    assert_eq!(rf.amp_shape.id, Some(4));
+    assert_eq!(rf.phase_shape.id, Some(6));
+    assert_eq!(block.gy.as_ref().unwrap().id(), Some(5));
+    assert_eq!(block.gz.as_ref().unwrap().id(), Some(9));
+    assert_eq!(block.adc.as_ref().unwrap().id, Some(3));
+
+    let reloaded = round_trip(&seq);
+    let block = &reloaded.blocks[0];
+    assert_eq!(block.rf.as_ref().unwrap().id, Some(7));
+    assert_eq!(block.rf.as_ref().unwrap().amp_shape.id, Some(4));
+    assert_eq!(block.gy.as_ref().unwrap().id(), Some(5));
+    assert_eq!(block.gz.as_ref().unwrap().id(), Some(9));
+    assert_eq!(block.adc.as_ref().unwrap().id, Some(3));
+}
+
+#[test]
+fn new_events_keep_loaded_ids() {
+    let source = "
+[VERSION]
+major 1
+minor 4
+revision 1
+
+[DEFINITIONS]
+AdcRasterTime 1e-07
+BlockDurationRaster 1e-05
+GradientRasterTime 1e-05
+RadiofrequencyRasterTime 1e-06
+
+[BLOCKS]
+1 10 1 0 0 0 1 0
+
+[RF]
+1 250 1 2 0 0 0 0
+
+[ADC]
+1 10 10000 0 0 0
+
+[SHAPES]
+
+shape_id 1
+num_samples 2
+1
+1
+
+shape_id 2
+num_samples 2
+0
+0
+";
+    let mut seq = Sequence::from_source(source).unwrap();
+    // New events in front of the loaded ones can't take their IDs
+    seq.blocks.insert(
+        0,
+        Block {
+            id: 2,
+            duration: 100e-6,
+            rf: Some(Arc::new(Rf::from_real(&[100.0, 50.0], 0.0))),
+            adc: Some(Arc::new(Adc::new(20, 1e-6, 0.0))),
+            ..Default::default()
+        },
+    );
+
+    let reloaded = round_trip(&seq);
+    let [new, loaded] = &reloaded.blocks[..] else {
+        panic!("expected two blocks");
+    };
+    let rf = loaded.rf.as_ref().unwrap();
+    assert_eq!(rf.id, Some(1));
+    assert_eq!(rf.amp_shape.id, Some(1));
+    assert_eq!(rf.phase_shape.id, Some(2));
+    assert_eq!(loaded.adc.as_ref().unwrap().id, Some(1));
+
+    let rf = new.rf.as_ref().unwrap();
+    assert_eq!(rf.id, Some(2));
+    assert_eq!(rf.amp_shape.id, Some(3));
+    // Identical shapes are shared
+    assert_eq!(rf.phase_shape.id, Some(2));
+    assert_eq!(new.adc.as_ref().unwrap().id, Some(2));
+}
+
+#[test]
+fn extended_trapezoid() {
+    let raster = TimeRaster::default();
+    let grad = make_extended_trapezoid(
+        &[0.0, 100e-6, 300e-6, 400e-6],
+        &[0.0, 500_000.0, 500_000.0, 0.0],
+        0.0,
+        &System::default(),
+        &raster,
+    )
+    .unwrap();
+    let grad = Arc::new(grad);
+    let mut builder = SequenceBuilder::new(raster.clone());
+    for _ in 0..2 {
+        builder.add_block(Block {
+            gx: Some(grad.clone()),
+            ..Default::default()
+        });
+    }
+    let seq = builder.build().unwrap();
+    assert!(seq.check_continuity(1.0).is_empty());
+
+    let reloaded = round_trip(&seq);
+    assert!(reloaded.check_continuity(1.0).is_empty());
+    let loaded = reloaded.blocks[0].gx.as_ref().unwrap();
+    assert_eq!(loaded.first(), 0.0);
+    assert_eq!(loaded.last(), 0.0);
+    for i in 0..=400 {
+        let t = i as f64 * 1e-6;
+        let (a, b) = (grad.sample(t, raster.grad), loaded.sample(t, raster.grad));
+        assert!((a - b).abs() < 1e-3, "{t}: {a} != {b}");
+    }
+    // Written as corners with a time shape
+    let source = seq.to_source();
+    assert!(source.contains("\n1 500000 1 2 0\n"));
+    assert!(source.contains("\nnum_samples 4\n0e0\n1e1\n3e1\n4e1\n"));
+}